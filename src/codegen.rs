@@ -1,16 +1,128 @@
 extern crate either;
 use inkwell;
 
+use std::collections::{HashMap, HashSet};
+use std::mem;
 use std::path::Path;
 use source_ast as ast;
+use diagnostic::{Diagnostic, Span};
+use inkwell::{FloatPredicate, IntPredicate};
+use inkwell::basic_block::BasicBlock;
+use inkwell::OptimizationLevel;
+use inkwell::execution_engine::ExecutionEngine;
+use inkwell::module::Linkage;
+use inkwell::targets::{CodeModel, FileType, InitializationConfig, RelocMode, Target, TargetMachine};
 use inkwell::types::{StructType, BasicTypeEnum, FunctionType};
-use inkwell::values::{BasicValue, BasicValueEnum};
+use inkwell::values::{BasicValue, BasicValueEnum, FunctionValue, IntValue, PhiValue, PointerValue};
 
-pub fn print_llvm_ir(ast_module: ast::Module, dest_file: &str) {
+/// The kinds of output the compiler can write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Emit {
+    LlvmIr,
+    Bitcode,
+    Assembly,
+    Object,
+}
+impl Emit {
+    /// The file extension used when no output path is given.
+    pub fn extension(&self) -> &'static str {
+        match *self {
+            Emit::LlvmIr => "ll",
+            Emit::Bitcode => "bc",
+            Emit::Assembly => "s",
+            Emit::Object => "o",
+        }
+    }
+}
+
+/// Compiles the module and writes it to `dest_file`. The module has to have
+/// passed `Module::calc_arieties` and the type checker.
+pub fn emit(ast_module: ast::Module, format: Emit, dest_file: &Path) -> Result<(), Diagnostic> {
+    let cgu = CodegenUnit::compile(ast_module)?;
+    let write_error = |e: &::std::fmt::Display| {
+        Diagnostic::error(format!("Could not write '{}': {}", dest_file.display(), e))
+    };
+    match format {
+        Emit::LlvmIr => cgu.module.print_to_file(dest_file).map_err(|e| write_error(&e)),
+        Emit::Bitcode => if cgu.module.write_bitcode_to_path(dest_file) {
+            Ok(())
+        } else {
+            Err(write_error(&"LLVM could not write the bitcode"))
+        },
+        Emit::Assembly => cgu.target_machine()?
+            .write_to_file(&cgu.module, FileType::Assembly, dest_file)
+            .map_err(|e| write_error(&e)),
+        Emit::Object => cgu.target_machine()?
+            .write_to_file(&cgu.module, FileType::Object, dest_file)
+            .map_err(|e| write_error(&e)),
+    }
+}
+
+/// Compiles the module and runs its `main` in LLVM's JIT. External words are
+/// resolved against the running process, which links libc. Returns what
+/// `main` returned.
+pub fn run(ast_module: ast::Module, args: &[String]) -> Result<i32, Diagnostic> {
+    match ast_module.functions.get("main") {
+        Some(main) => match signature(&main.typ).1.as_slice() {
+            [ast::SimaType::Basic { kind, .. }] if *kind != ast::BasicKind::Float => {}
+            _ => return Err(Diagnostic::error("'main' has to return an integer to be run")
+                .with_primary(main.span, format!("declared as {}", main.typ))),
+        },
+        None => return Err(Diagnostic::error("The module has no 'main' word to run")),
+    }
+    let cgu = CodegenUnit::compile(ast_module)?;
+    let main = cgu.module.get_function("main").unwrap();
+    let engine = cgu.execution_engine()?;
+    let args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+    let code = unsafe { engine.run_function_as_main(&main, &args) };
+    Ok(code as i32)
+}
+
+/// A number on the data stack of the REPL. Integers are kept zero extended
+/// to 64 bits, their Sima type tells how to read them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Constant {
+    Integer(u64),
+    Float(f64),
+}
+
+/// Runs `expr` in the JIT on top of the values in `stack` and returns the
+/// stack it leaves behind, whose types the checker found to be `result`.
+/// Only integers of up to 64 bits and floats can be passed in and out.
+pub fn evaluate(ast_module: ast::Module, stack: &[(Constant, ast::SimaType)], expr: &ast::Expression, result: &[ast::SimaType]) -> Result<Vec<Constant>, Diagnostic> {
     let mut cgu = CodegenUnit::new(&ast_module.id);
-    cgu.build_module(ast_module);
-    cgu.module.verify(true);
-    cgu.module.print_to_file(Path::new(dest_file)).unwrap();
+    cgu.build_module(ast_module)?;
+    cgu.build_evaluation(stack, expr, result)?;
+    cgu.verify()?;
+    let engine = cgu.execution_engine()?;
+    let address = engine.get_function_address("repl.line")
+        .map_err(|e| Diagnostic::error(format!("Could not look up the compiled line: {:?}", e)))?;
+    let line: extern "C" fn(*mut u64, *mut f64) = unsafe { mem::transmute(address) };
+    let mut integers = vec![0u64; result.len()];
+    let mut floats = vec![0f64; result.len()];
+    line(integers.as_mut_ptr(), floats.as_mut_ptr());
+    Ok(result.iter().enumerate().map(|(index, typ)| match *typ {
+        ast::SimaType::Basic { kind: ast::BasicKind::Float, .. } => Constant::Float(floats[index]),
+        _ => Constant::Integer(integers[index]),
+    }).collect())
+}
+
+/// An entry of the symbolic stack. Values remember their Sima type, which
+/// decides e.g. between signed and unsigned instructions. Quotations stay
+/// unlowered until they get consumed, because only then is the type of the
+/// function they lift to known.
+#[derive(Debug, Clone)]
+enum StackValue {
+    Value(BasicValueEnum, ast::SimaType),
+    Quotation(ast::Expression),
+}
+
+/// The argument and return types of a function type.
+fn signature(typ: &ast::SimaType) -> (Vec<ast::SimaType>, Vec<ast::SimaType>) {
+    match *typ {
+        ast::SimaType::Function { ref args, ref ret } => (args.clone(), ret.clone()),
+        _ => (Vec::new(), Vec::new()),
+    }
 }
 
 #[derive(Debug)]
@@ -18,8 +130,16 @@ struct CodegenUnit {
     context: inkwell::context::Context,
     module: inkwell::module::Module,
     builder: inkwell::builder::Builder,
-    stack: Vec<BasicValueEnum>,
+    stack: Vec<StackValue>,
     opaque_type: StructType,
+    /// The LLVM structs of the named types, created when they are first used.
+    named_types: HashMap<String, StructType>,
+    types: HashMap<String, ast::NamedType>,
+    signatures: HashMap<String, ast::SimaType>,
+    /// The words that are only declared, which are called the way C
+    /// expects.
+    externals: HashSet<String>,
+    lifted: usize,
 }
 
 impl CodegenUnit {
@@ -35,109 +155,984 @@ impl CodegenUnit {
             builder,
             stack,
             opaque_type,
+            named_types: HashMap::new(),
+            types: HashMap::new(),
+            signatures: HashMap::new(),
+            externals: HashSet::new(),
+            lifted: 0,
         }
     }
 }
 
 impl CodegenUnit {
-    fn build_module(&mut self, ast_module: ast::Module){
-    	ast_module.calc_arieties();
-        for (id, fun) in ast_module.functions.iter() {
-            let function_type = self.build_function_type(&fun.typ);
+    /// Builds and verifies the LLVM module for a checked Sima module.
+    fn compile(ast_module: ast::Module) -> Result<CodegenUnit, Diagnostic> {
+        let mut cgu = CodegenUnit::new(&ast_module.id);
+        cgu.build_module(ast_module)?;
+        cgu.verify()?;
+        Ok(cgu)
+    }
+
+    fn verify(&self) -> Result<(), Diagnostic> {
+        if self.module.verify(true) {
+            Ok(())
+        } else {
+            Err(Diagnostic::error("LLVM rejected the generated module"))
+        }
+    }
+
+    /// A JIT for the module. Words that are only declared are looked up in
+    /// the running process.
+    fn execution_engine(&self) -> Result<ExecutionEngine, Diagnostic> {
+        Target::initialize_native(&InitializationConfig::default())
+            .map_err(|e| Diagnostic::error(format!("Could not set up the native target: {}", e)))?;
+        self.module.create_jit_execution_engine(OptimizationLevel::None)
+            .map_err(|e| Diagnostic::error(format!("Could not create the JIT: {}", e)))
+    }
+
+    /// A target machine for the host, which assembly and object files are
+    /// generated for.
+    fn target_machine(&self) -> Result<TargetMachine, Diagnostic> {
+        let target_error = |e: &::std::fmt::Display| {
+            Diagnostic::error(format!("Could not set up the native target: {}", e))
+        };
+        Target::initialize_native(&InitializationConfig::default())
+            .map_err(|e| target_error(&e))?;
+        let triple = TargetMachine::get_default_triple();
+        let target = Target::from_triple(&triple).map_err(|e| target_error(&e))?;
+        let machine = target.create_target_machine(
+            &triple,
+            "generic",
+            "",
+            OptimizationLevel::Default,
+            RelocMode::PIC,
+            CodeModel::Default,
+        );
+        machine.ok_or_else(|| target_error(&format!("no target machine for '{}'", triple)))
+    }
+
+    fn build_module(&mut self, ast_module: ast::Module) -> Result<(), Diagnostic> {
+        self.types = ast_module.types.clone();
+        for (id, fun) in ast_module.iter() {
+            let function_type = match fun.expr {
+                Some(_) => self.build_function_type(&fun.typ, fun.variadic, fun.span)?,
+                None => {
+                    self.externals.insert(id.clone());
+                    self.build_function_type(&c_signature(&fun.typ, fun.span)?, fun.variadic, fun.span)?
+                }
+            };
             self.module.add_function(&id, &function_type, None);
+            self.signatures.insert(id.clone(), fun.typ.clone());
         }
-        for (id, fun) in ast_module.functions.iter(){
+        for (id, fun) in ast_module.iter(){
             if let Some(ref expr) = fun.expr{
-            	let function_value = self.module.get_function(id).unwrap();
-		        let basic_block = self.context.append_basic_block(&function_value, "entry");
-		        self.builder.position_at_end(&basic_block);
-		        self.stack.clear();
-		        for p in function_value.params(){
-		            self.put(p, 0);
-        		}
-        		self.build_expression(&expr, 0);
-        		if self.stack.is_empty(){
-        			self.builder.build_return(None);
-        		}else{
-        			let ret = &self.stack.pop().unwrap() as &BasicValue;
-        			self.builder.build_return(Some(ret));
-        		}
+                let function_value = self.module.get_function(id).unwrap();
+                self.build_body(&function_value, expr, &fun.typ)?;
             }
         }
+        Ok(())
+    }
+
+    fn build_body(&mut self, function_value: &FunctionValue, expr: &ast::Expression, typ: &ast::SimaType) -> Result<(), Diagnostic> {
+        let basic_block = self.context.append_basic_block(function_value, "entry");
+        self.builder.position_at_end(&basic_block);
+        self.stack.clear();
+        let (arg_types, ret_types) = signature(typ);
+        for (p, t) in function_value.params().into_iter().zip(arg_types){
+            self.put(StackValue::Value(p, t), 0);
+        }
+        self.build_expression(expr, 0)?;
+        match ret_types.len() {
+            0 => {
+                self.builder.build_return(None);
+            }
+            1 => {
+                let value = self.take(0);
+                let ret = self.lower(value, ret_types.first(), expr.span)?;
+                self.builder.build_return(Some(&ret as &BasicValue));
+            }
+            count => {
+                // several results are returned as one struct, the value that
+                // was pushed first becomes the first field
+                let values = self.take_args(count, &ret_types, 0, expr.span)?;
+                let mut ret = self.build_struct_type(&ret_types, expr.span)?.get_undef();
+                for (index, val) in values.iter().enumerate() {
+                    ret = self.builder
+                        .build_insert_value(&ret, val as &BasicValue, index as u32, "")
+                        .into_struct_value();
+                }
+                self.builder.build_return(Some(&ret as &BasicValue));
+            }
+        }
+        Ok(())
+    }
+
+    /// Builds `repl.line`, which runs `expr` on top of the constants in
+    /// `stack`. It stores the stack it leaves into one buffer for integers
+    /// and one for floats, each value at its index in the stack.
+    fn build_evaluation(&mut self, stack: &[(Constant, ast::SimaType)], expr: &ast::Expression, result: &[ast::SimaType]) -> Result<(), Diagnostic> {
+        let i64_type = self.context.i64_type();
+        let f64_type = self.context.f64_type();
+        let address_space = inkwell::AddressSpace::Generic;
+        let function_type = self.context.void_type()
+            .fn_type(&[&i64_type.ptr_type(address_space), &f64_type.ptr_type(address_space)], false);
+        let function_value = self.module.add_function("repl.line", &function_type, None);
+        let basic_block = self.context.append_basic_block(&function_value, "entry");
+        self.builder.position_at_end(&basic_block);
+        self.stack.clear();
+
+        for &(constant, ref typ) in stack {
+            let value = match (constant, self.build_basic_type(typ, expr.span)?) {
+                (Constant::Integer(bits), BasicTypeEnum::IntType(t)) => t.const_int(bits, false).as_basic_value_enum(),
+                (Constant::Float(f), BasicTypeEnum::FloatType(t)) => t.const_float(f).as_basic_value_enum(),
+                _ => return Err(Diagnostic::error(format!("A value of type {} cannot be kept on the stack", typ))),
+            };
+            self.put(StackValue::Value(value, typ.clone()), 0);
+        }
+        self.build_expression(expr, 0)?;
+
+        let integers = function_value.get_nth_param(0).unwrap().into_pointer_value();
+        let floats = function_value.get_nth_param(1).unwrap().into_pointer_value();
+        let values = self.take_args(result.len(), result, 0, expr.span)?;
+        for (index, (val, typ)) in values.into_iter().zip(result).enumerate() {
+            let index = i64_type.const_int(index as u64, false);
+            match val {
+                BasicValueEnum::FloatValue(val) => {
+                    let slot = self.builder.build_gep(&floats, &[&index], "");
+                    let val = self.builder.build_float_cast(&val, &f64_type, "");
+                    self.builder.build_store(&slot, &val);
+                }
+                BasicValueEnum::IntValue(val) if val.get_type().get_bit_width() <= 64 => {
+                    let slot = self.builder.build_gep(&integers, &[&index], "");
+                    let val = if val.get_type().get_bit_width() == 64 {
+                        val
+                    } else {
+                        self.builder.build_int_z_extend(&val, &i64_type, "")
+                    };
+                    self.builder.build_store(&slot, &val);
+                }
+                _ => return Err(Diagnostic::error(format!("A value of type {} cannot be kept on the stack", typ))
+                    .with_primary(expr.span, "leaves it on the stack, where only numbers can stay between lines")),
+            }
+        }
+        self.builder.build_return(None);
+        Ok(())
+    }
 
+    /// Puts what a call returned onto the stack, unpacking struct returns of
+    /// words with several results.
+    fn put_returned(&mut self, val: BasicValueEnum, ret_types: &[ast::SimaType], depth: usize) {
+        if ret_types.len() == 1 {
+            self.put(StackValue::Value(val, ret_types[0].clone()), depth);
+            return;
+        }
+        let aggregate = val.into_struct_value();
+        for (index, typ) in ret_types.iter().enumerate() {
+            let field = self.builder.build_extract_value(&aggregate, index as u32, "");
+            self.put(StackValue::Value(field, typ.clone()), depth);
+        }
     }
 
-    fn put(&mut self, value: BasicValueEnum, depth: usize){
-    	let index = self.stack.len() - depth;
-    	self.stack.insert(index, value);
+    /// Builds the body of a quotation as a private function of type `typ`.
+    /// The caller's insertion point and stack are restored afterwards.
+    fn lift(&mut self, inner: &ast::Expression, typ: &ast::SimaType) -> Result<FunctionValue, Diagnostic> {
+        let effect = ast::StackEffect {
+            in_ariety: inner.in_ariety(),
+            out_ariety: inner.out_ariety(),
+        };
+        if typ.effect() != Some(effect) {
+            return Err(Diagnostic::error(format!("Quotation with stack effect {} is used where {} is expected", effect, typ))
+                .with_primary(inner.span, "this quotation"));
+        }
+        let name = format!("quotation.{}", self.lifted);
+        self.lifted += 1;
+        let function_type = self.build_function_type(typ, false, inner.span)?;
+        let function_value = self.module.add_function(&name, &function_type, Some(&Linkage::PrivateLinkage));
+
+        let saved_block = self.builder.get_insert_block();
+        let saved_stack = mem::replace(&mut self.stack, Vec::new());
+        let body = self.build_body(&function_value, inner, typ);
+        self.stack = saved_stack;
+        if let Some(block) = saved_block {
+            self.builder.position_at_end(&block);
+        }
+        body.map(|_| function_value)
     }
-    fn take(&mut self, depth: usize) -> BasicValueEnum{
-    	let index = self.stack.len() - depth - 1;
-    	self.stack.remove(index)
+
+    /// Turns a stack entry into an LLVM value, lifting quotations to function
+    /// pointers of the expected type.
+    fn lower(&mut self, value: StackValue, typ: Option<&ast::SimaType>, span: Span) -> Result<BasicValueEnum, Diagnostic> {
+        match value {
+            StackValue::Value(val, _) => Ok(val),
+            StackValue::Quotation(inner) => {
+                let typ = match typ {
+                    Some(t) if t.effect().is_some() => t,
+                    _ => return Err(Diagnostic::error("Quotation is used where no function type is expected")
+                        .with_primary(span, "the quotation is consumed here")
+                        .with_secondary(inner.span, "quotation")),
+                };
+                let function_value = self.lift(&inner, typ)?;
+                Ok(function_value.as_global_value().as_pointer_value().as_basic_value_enum())
+            }
+        }
     }
 
-    fn build_expression(&mut self, expr: &ast::Expression, depth: usize) {
+    fn put(&mut self, value: StackValue, depth: usize){
+        let index = self.stack.len() - depth;
+        self.stack.insert(index, value);
+    }
+    fn take(&mut self, depth: usize) -> StackValue{
+        let index = self.stack.len() - depth - 1;
+        self.stack.remove(index)
+    }
+    /// Takes a value that is used as data, not as a quotation.
+    fn take_value(&mut self, depth: usize, span: Span) -> Result<(BasicValueEnum, ast::SimaType), Diagnostic>{
+        match self.take(depth) {
+            StackValue::Value(val, typ) => Ok((val, typ)),
+            StackValue::Quotation(inner) => Err(Diagnostic::error("Quotation is used where a value is expected")
+                .with_primary(span, "expects a value")
+                .with_secondary(inner.span, "quotation")),
+        }
+    }
+
+    /// Takes `count` arguments off the stack, lowered to the given parameter
+    /// types, in the order they were pushed.
+    fn take_args(&mut self, count: usize, types: &[ast::SimaType], depth: usize, span: Span) -> Result<Vec<BasicValueEnum>, Diagnostic>{
+        let mut args = Vec::with_capacity(count);
+        for i in (0..count).rev(){
+            let value = self.take(depth);
+            args.push(self.lower(value, types.get(i), span)?);
+        }
+        args.reverse();
+        Ok(args)
+    }
+
+    /// Applies C's default argument promotions to an extra argument of a
+    /// variadic call: integers narrower than `int` are extended to 32 bits
+    /// and `float`s become `double`s.
+    fn promote(&mut self, val: BasicValueEnum, typ: &ast::SimaType) -> BasicValueEnum {
+        match (val, typ) {
+            (BasicValueEnum::IntValue(val), &ast::SimaType::Basic { width, .. }) if width < 32 => {
+                let i32_type = self.context.i32_type();
+                if typ.is_signed() && width > 1 {
+                    self.builder.build_int_s_extend(&val, &i32_type, "").as_basic_value_enum()
+                } else {
+                    self.builder.build_int_z_extend(&val, &i32_type, "").as_basic_value_enum()
+                }
+            }
+            (BasicValueEnum::FloatValue(val), &ast::SimaType::Basic { width, .. }) if width < 64 => {
+                let f64_type = self.context.f64_type();
+                self.builder.build_float_cast(&val, &f64_type, "").as_basic_value_enum()
+            }
+            _ => val,
+        }
+    }
+
+    /// Passes an array to C the way C passes arrays: as a pointer to its
+    /// first element, here of a copy on the stack.
+    fn decay(&mut self, val: BasicValueEnum) -> BasicValueEnum {
+        match val {
+            BasicValueEnum::ArrayValue(array) => {
+                let slot = self.build_entry_alloca(&array.get_type());
+                self.builder.build_store(&slot, &array);
+                let zero = self.context.i64_type().const_int(0, false);
+                self.builder.build_gep(&slot, &[&zero, &zero], "").as_basic_value_enum()
+            }
+            val => val,
+        }
+    }
+
+    /// Runs a quotation on the stack: quotation literals are inlined, function
+    /// pointers are called with `in_ariety` arguments.
+    fn apply(&mut self, quotation: StackValue, in_ariety: usize, depth: usize, span: Span) -> Result<(), Diagnostic> {
+        use self::either::Either;
+
+        match quotation {
+            StackValue::Quotation(inner) => self.build_expression(&inner, depth)?,
+            StackValue::Value(val, typ) => {
+                let callee = val.into_pointer_value();
+                let (arg_types, ret_types) = signature(&typ);
+                let args = self.take_args(in_ariety, &arg_types, depth, span)?;
+                let args : Vec<&BasicValue> = args.iter().map(|a| a as &BasicValue).collect();
+                let ret = self.builder.build_call(&callee, &args, "", false);
+                if let Either::Left(val) = ret {
+                    self.put_returned(val, &ret_types, depth);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Turns a stack value into an `i1`, comparing wider integers against 0.
+    fn build_condition(&mut self, value: StackValue, span: Span) -> Result<IntValue, Diagnostic> {
+        let value = self.lower(value, None, span)?.into_int_value();
+        if value.get_type().get_bit_width() == 1 {
+            return Ok(value);
+        }
+        let zero = value.get_type().const_int(0, false);
+        Ok(self.builder.build_int_compare(IntPredicate::NE, &value, &zero, ""))
+    }
+
+    /// Joins the stacks that several predecessors of the current block leave
+    /// behind. Only the `count` values at `depth` may differ between them;
+    /// those get a phi node each.
+    fn merge_stacks(&mut self, branches: Vec<(Vec<StackValue>, BasicBlock)>, count: usize, depth: usize, span: Span) -> Result<Vec<StackValue>, Diagnostic> {
+        let mut merged = branches[0].0.clone();
+        let end = merged.len() - depth;
+        for index in end - count..end {
+            let mut values: Vec<(BasicValueEnum, ast::SimaType)> = Vec::with_capacity(branches.len());
+            for &(ref stack, _) in branches.iter() {
+                match stack[index] {
+                    StackValue::Value(val, ref typ) => values.push((val, typ.clone())),
+                    StackValue::Quotation(_) => return Err(Diagnostic::error("Quotations cannot be merged across branches")
+                        .with_primary(span, "a branch leaves a quotation on the stack")),
+                }
+            }
+            let typ = values[0].1.clone();
+            if let Some(&(_, ref other)) = values.iter().find(|&&(_, ref t)| *t != typ) {
+                return Err(Diagnostic::error("Branches leave values of different types on the stack")
+                    .with_primary(span, format!("one branch leaves {}, another {}", typ, other)));
+            }
+            if values.iter().all(|&(val, _)| val == values[0].0) {
+                continue;
+            }
+            let phi = self.builder.build_phi(&values[0].0.get_type(), "");
+            for (&(ref val, _), &(_, ref block)) in values.iter().zip(branches.iter()) {
+                phi.add_incoming(&[(val as &BasicValue, block)]);
+            }
+            merged[index] = StackValue::Value(phi.as_basic_value(), typ);
+        }
+        Ok(merged)
+    }
+
+    /// Replaces the `count` values at `depth` by phi nodes at the top of a
+    /// loop header, with the current values coming in from `predecessor`.
+    /// The back edge is added by `add_carried_incoming` once the loop body
+    /// is built.
+    fn build_carried_phis(&mut self, count: usize, depth: usize, predecessor: &BasicBlock, span: Span) -> Result<Vec<PhiValue>, Diagnostic> {
+        let mut phis = Vec::with_capacity(count);
+        let end = self.stack.len() - depth;
+        for index in end - count..end {
+            let (val, typ) = match self.stack[index] {
+                StackValue::Value(val, ref typ) => (val, typ.clone()),
+                StackValue::Quotation(_) => return Err(carried_quotation(span)),
+            };
+            let phi = self.builder.build_phi(&val.get_type(), "");
+            phi.add_incoming(&[(&val as &BasicValue, predecessor)]);
+            self.stack[index] = StackValue::Value(phi.as_basic_value(), typ);
+            phis.push(phi);
+        }
+        Ok(phis)
+    }
+
+    /// Closes the loop started by `build_carried_phis`: the values the loop
+    /// body left at `depth` come in from the current block.
+    fn add_carried_incoming(&mut self, phis: &[PhiValue], depth: usize, span: Span) -> Result<(), Diagnostic> {
+        let latch_block = self.builder.get_insert_block().unwrap();
+        let end = self.stack.len() - depth;
+        for (phi, index) in phis.iter().zip(end - phis.len()..end) {
+            match self.stack[index] {
+                StackValue::Value(val, _) => phi.add_incoming(&[(&val as &BasicValue, &latch_block)]),
+                StackValue::Quotation(_) => return Err(carried_quotation(span)),
+            }
+        }
+        Ok(())
+    }
+
+    fn current_function(&self) -> FunctionValue {
+        self.builder.get_insert_block().unwrap().get_parent().unwrap()
+    }
+
+    /// Puts `bytes` into a private constant global and returns a pointer to
+    /// its first byte. Unlike `build_global_string`, the bytes do not have to
+    /// be UTF-8 and are not terminated with a NUL.
+    fn build_bytes(&mut self, bytes: &[u8]) -> PointerValue {
+        let i8_type = self.context.i8_type();
+        let values: Vec<IntValue> = bytes.iter().map(|&b| i8_type.const_int(b as u64, false)).collect();
+        let array = i8_type.const_array(&values.iter().collect::<Vec<_>>());
+        let global = self.module.add_global(&i8_type.array_type(bytes.len() as u32), Some(&inkwell::AddressSpace::Generic), "string");
+        global.set_initializer(&array);
+        global.set_constant(true);
+        global.set_linkage(&Linkage::PrivateLinkage);
+        let i32_0 = self.context.i32_type().const_int(0, false);
+        self.builder.build_gep(&global.as_pointer_value(), &[&i32_0, &i32_0], "")
+    }
+
+    fn build_expression(&mut self, expr: &ast::Expression, depth: usize) -> Result<(), Diagnostic> {
         use inkwell::values::BasicValue;
-        use source_ast::Expression::*;
+        use source_ast::ExpressionKind::*;
         use self::either::Either;
 
-        match *expr{
-        	Concat{ref left, ref right} => {
-        		self.build_expression(left, depth);
-        		self.build_expression(right, depth);
-        	}
-        	Sidecat{ref left, ref right} => {
-        		self.build_expression(left, depth + right.in_ariety());
-        		self.build_expression(right, depth);
-        	},
-            Block { .. } => unimplemented!(),
-            StringLiteral(ref lit) => {
-                let str_arr = self.builder
-                    .build_global_string(&lit, "string")
-                    .as_pointer_value();
-                let i32_0 = self.context.i32_type().const_int(0, false);
-                let str_ptr = self.builder.build_gep(&str_arr, &[&i32_0, &i32_0], "");
-                self.put(str_ptr.as_basic_value_enum(), depth);
+        let span = expr.span;
+        match expr.kind{
+            Concat{ref left, ref right} => {
+                self.build_expression(left, depth)?;
+                self.build_expression(right, depth)?;
+            }
+            Sidecat{ref left, ref right} => {
+                self.build_expression(left, depth + right.in_ariety())?;
+                self.build_expression(right, depth)?;
+            },
+            Block { ref inner } => {
+                self.put(StackValue::Quotation((**inner).clone()), depth);
+            }
+            StringLiteral { ref bytes, .. } => {
+                let mut terminated = bytes.clone();
+                terminated.push(0);
+                let str_ptr = self.build_bytes(&terminated);
+                let typ = ast::SimaType::Pointer { target: Box::new(ast::SimaType::Basic { kind: ast::BasicKind::Signed, width: 8 }) };
+                self.put(StackValue::Value(str_ptr.as_basic_value_enum(), typ), depth);
+            }
+            ByteString { ref bytes, .. } => {
+                let str_ptr = self.build_bytes(bytes);
+                let typ = ast::SimaType::Pointer { target: Box::new(ast::SimaType::Basic { kind: ast::BasicKind::Signed, width: 8 }) };
+                self.put(StackValue::Value(str_ptr.as_basic_value_enum(), typ), depth);
+                let len = self.context.i64_type().const_int(bytes.len() as u64, false);
+                let typ = ast::SimaType::Basic { kind: ast::BasicKind::Unsigned, width: 64 };
+                self.put(StackValue::Value(len.as_basic_value_enum(), typ), depth);
+            }
+            Identifier {ref id, ..} if !self.signatures.contains_key(id) => {
+                // the constructor of a record
+                let fields: Vec<ast::SimaType> = match self.types[id].definition {
+                    ast::TypeDefinition::Record { ref fields } => fields.iter().map(|&(_, ref t)| t.clone()).collect(),
+                    ast::TypeDefinition::Opaque | ast::TypeDefinition::Variant { .. } => unreachable!(),
+                };
+                let values = self.take_args(fields.len(), &fields, depth, span)?;
+                let mut record = self.build_named_type(id, span)?.get_undef();
+                for (index, val) in values.iter().enumerate() {
+                    record = self.builder
+                        .build_insert_value(&record, val as &BasicValue, index as u32, "")
+                        .into_struct_value();
+                }
+                let typ = ast::SimaType::Named(id.clone());
+                self.put(StackValue::Value(record.as_basic_value_enum(), typ), depth);
             }
             Identifier {ref id, ref in_ariety, ..} => {
-                let function = self.module.get_function(&id);
-                assert!(function.is_some(), "Unknown Identifier '{}'", id);
-                let function = function.unwrap();
-                let mut args : Vec<Box<BasicValue>> = Vec::new(); 
-                for _ in 0..in_ariety.get(){
-                	args.push(Box::new(self.take(depth)));
-                }
-                args.reverse(); 
-            	let args : Vec<_> = args.iter().map(|t| t.as_ref()).collect();
+                let function = match self.module.get_function(&id) {
+                    Some(function) => function,
+                    None => return Err(Diagnostic::error(format!("Unknown Identifier '{}'", id))
+                        .with_primary(span, "not declared in this module")),
+                };
+                let (arg_types, ret_types) = signature(&self.signatures[id]);
+                // the extra arguments of a variadic call are on top of the
+                // declared ones, and the type checker made sure C can take them
+                let mut extras = Vec::new();
+                for _ in arg_types.len()..in_ariety.get() {
+                    extras.push(self.take_value(depth, span)?);
+                }
+                extras.reverse();
+                let mut args = self.take_args(arg_types.len(), &arg_types, depth, span)?;
+                for (val, typ) in extras {
+                    args.push(self.promote(val, &typ));
+                }
+                if self.externals.contains(id) {
+                    args = args.into_iter().map(|val| self.decay(val)).collect();
+                }
+                let args : Vec<&BasicValue> = args.iter().map(|a| a as &BasicValue).collect();
                 let ret = self.builder.build_call(&function, &args, "", false);
                 if let Either::Left(val) = ret {
-                    self.put(val, depth);
+                    self.put_returned(val, &ret_types, depth);
                 }
             }
-            Number(ref num) => {
-            	let i : u64 = num.parse().unwrap();
-            	let val = self.context.i32_type().const_int(i, false);
-            	self.put(val.as_basic_value_enum(), depth);
+            Apply {ref in_ariety, ..} => {
+                let quotation = self.take(depth);
+                self.apply(quotation, in_ariety.get() - 1, depth, span)?;
+            }
+            Conditional {ref in_ariety, ref out_ariety} => {
+                let otherwise = self.take(depth);
+                let then = self.take(depth);
+                let condition = self.take(depth);
+                let condition = self.build_condition(condition, span)?;
+
+                let function = self.current_function();
+                let then_block = self.context.append_basic_block(&function, "then");
+                let else_block = self.context.append_basic_block(&function, "else");
+                let merge_block = self.context.append_basic_block(&function, "merge");
+                self.builder.build_conditional_branch(&condition, &then_block, &else_block);
+
+                let branch_in = in_ariety.get() - 3;
+                let mut branches = Vec::new();
+                for &(ref quotation, ref block) in [(then, then_block), (otherwise, else_block)].iter() {
+                    let saved_stack = self.stack.clone();
+                    self.builder.position_at_end(block);
+                    self.apply(quotation.clone(), branch_in, depth, span)?;
+                    self.builder.build_unconditional_branch(&merge_block);
+                    let end_block = self.builder.get_insert_block().unwrap();
+                    branches.push((mem::replace(&mut self.stack, saved_stack), end_block));
+                }
+
+                self.builder.position_at_end(&merge_block);
+                self.stack = self.merge_stacks(branches, out_ariety.get(), depth, span)?;
+            }
+            While {ref condition, ref body} => {
+                let body_quotation = self.take(depth);
+                let condition_quotation = self.take(depth);
+                let carried = expr.out_ariety();
+
+                let function = self.current_function();
+                let entry_block = self.builder.get_insert_block().unwrap();
+                let header_block = self.context.append_basic_block(&function, "while.header");
+                let body_block = self.context.append_basic_block(&function, "while.body");
+                let exit_block = self.context.append_basic_block(&function, "while.exit");
+                self.builder.build_unconditional_branch(&header_block);
+
+                self.builder.position_at_end(&header_block);
+                let phis = self.build_carried_phis(carried, depth, &entry_block, span)?;
+                self.apply(condition_quotation, condition.get().in_ariety, depth, span)?;
+                let flag = self.take(depth);
+                let flag = self.build_condition(flag, span)?;
+                self.builder.build_conditional_branch(&flag, &body_block, &exit_block);
+                let exit_stack = self.stack.clone();
+
+                self.builder.position_at_end(&body_block);
+                self.apply(body_quotation, body.get().in_ariety, depth, span)?;
+                self.add_carried_incoming(&phis, depth, span)?;
+                self.builder.build_unconditional_branch(&header_block);
+
+                self.builder.position_at_end(&exit_block);
+                self.stack = exit_stack;
+            }
+            Times {ref body} => {
+                let body_quotation = self.take(depth);
+                // the count is below the values the body carries around
+                let carried = body.get().in_ariety;
+                let (count, count_type) = self.take_value(depth + carried, span)?;
+                let count = count.into_int_value();
+
+                let function = self.current_function();
+                let entry_block = self.builder.get_insert_block().unwrap();
+                let header_block = self.context.append_basic_block(&function, "times.header");
+                let body_block = self.context.append_basic_block(&function, "times.body");
+                let exit_block = self.context.append_basic_block(&function, "times.exit");
+                self.builder.build_unconditional_branch(&header_block);
+
+                self.builder.position_at_end(&header_block);
+                let counter = self.builder.build_phi(&count.get_type(), "counter");
+                counter.add_incoming(&[(&count as &BasicValue, &entry_block)]);
+                let phis = self.build_carried_phis(carried, depth, &entry_block, span)?;
+                let remaining = counter.as_basic_value().into_int_value();
+                let zero = count.get_type().const_int(0, false);
+                let greater = if count_type.is_signed() { IntPredicate::SGT } else { IntPredicate::UGT };
+                let flag = self.builder.build_int_compare(greater, &remaining, &zero, "");
+                self.builder.build_conditional_branch(&flag, &body_block, &exit_block);
+                let exit_stack = self.stack.clone();
+
+                self.builder.position_at_end(&body_block);
+                self.apply(body_quotation, carried, depth, span)?;
+                let one = count.get_type().const_int(1, false);
+                let next = self.builder.build_int_sub(&remaining, &one, "");
+                let latch_block = self.builder.get_insert_block().unwrap();
+                counter.add_incoming(&[(&next as &BasicValue, &latch_block)]);
+                self.add_carried_incoming(&phis, depth, span)?;
+                self.builder.build_unconditional_branch(&header_block);
+
+                self.builder.position_at_end(&exit_block);
+                self.stack = exit_stack;
+            }
+            Number{value, ref typ, ..} => {
+                // the type checker has typed the literal and checked its range
+                let (kind, width) = typ.get().unwrap();
+                let value = value.unwrap();
+                if value < i64::min_value() as i128 || value > u64::max_value() as i128 {
+                    return Err(Diagnostic::error("Integer literals beyond 64 bits are not supported")
+                        .with_primary(span, "does not fit into 64 bits"));
+                }
+                let val = self.context.custom_width_int_type(width).const_int(value as u64, value < 0);
+                let typ = ast::SimaType::Basic { kind, width };
+                self.put(StackValue::Value(val.as_basic_value_enum(), typ), depth);
+            },
+            Float{ref literal, ref width} => {
+                let f : f64 = literal.parse().unwrap();
+                let typ = ast::SimaType::Basic { kind: ast::BasicKind::Float, width: width.get().unwrap_or(64) };
+                let val = self.build_basic_type(&typ, span)?.into_float_type().const_float(f);
+                self.put(StackValue::Value(val.as_basic_value_enum(), typ), depth);
+            },
+            Arithmetic(operator) => {
+                let (right, typ) = self.take_value(depth, span)?;
+                let (left, left_typ) = self.take_value(depth, span)?;
+                if left_typ != typ {
+                    return Err(operand_mismatch(&operator, &left_typ, &typ, span));
+                }
+                let result = match typ {
+                    // inkwell's builder only takes scalar operands here
+                    ast::SimaType::Vector { ref element, length } => {
+                        let (left, right) = (left.into_vector_value(), right.into_vector_value());
+                        let mut result = left;
+                        for lane in 0..length {
+                            let lane = self.context.i32_type().const_int(lane as u64, false);
+                            let l = self.builder.build_extract_element(&left, &lane, "");
+                            let r = self.builder.build_extract_element(&right, &lane, "");
+                            let value = self.build_arithmetic(operator, l, r, element);
+                            result = self.builder.build_insert_element(&result, &value, &lane, "");
+                        }
+                        result.as_basic_value_enum()
+                    }
+                    _ => self.build_arithmetic(operator, left, right, &typ),
+                };
+                self.put(StackValue::Value(result, typ), depth);
+            },
+            Negate => {
+                let (val, typ) = self.take_value(depth, span)?;
+                let result = match typ {
+                    ast::SimaType::Vector { ref element, length } => {
+                        let vector = val.into_vector_value();
+                        let mut result = vector;
+                        for lane in 0..length {
+                            let lane = self.context.i32_type().const_int(lane as u64, false);
+                            let value = self.builder.build_extract_element(&vector, &lane, "");
+                            let value = self.build_negate(value, element);
+                            result = self.builder.build_insert_element(&result, &value, &lane, "");
+                        }
+                        result.as_basic_value_enum()
+                    }
+                    _ => self.build_negate(val, &typ),
+                };
+                self.put(StackValue::Value(result, typ), depth);
+            },
+            Comparison(comparison) => {
+                use source_ast::Comparison::*;
+                let (right, typ) = self.take_value(depth, span)?;
+                let (left, left_typ) = self.take_value(depth, span)?;
+                if left_typ != typ {
+                    return Err(operand_mismatch(&comparison, &left_typ, &typ, span));
+                }
+                let result = if typ.is_float() {
+                    let predicate = match comparison {
+                        Equal => FloatPredicate::OEQ,
+                        NotEqual => FloatPredicate::UNE,
+                        Less => FloatPredicate::OLT,
+                        Greater => FloatPredicate::OGT,
+                        LessEqual => FloatPredicate::OLE,
+                        GreaterEqual => FloatPredicate::OGE,
+                    };
+                    self.builder.build_float_compare(predicate, &left.into_float_value(), &right.into_float_value(), "")
+                } else {
+                    let predicate = match (comparison, typ.is_signed()) {
+                        (Equal, _) => IntPredicate::EQ,
+                        (NotEqual, _) => IntPredicate::NE,
+                        (Less, true) => IntPredicate::SLT,
+                        (Less, false) => IntPredicate::ULT,
+                        (Greater, true) => IntPredicate::SGT,
+                        (Greater, false) => IntPredicate::UGT,
+                        (LessEqual, true) => IntPredicate::SLE,
+                        (LessEqual, false) => IntPredicate::ULE,
+                        (GreaterEqual, true) => IntPredicate::SGE,
+                        (GreaterEqual, false) => IntPredicate::UGE,
+                    };
+                    self.builder.build_int_compare(predicate, &left.into_int_value(), &right.into_int_value(), "")
+                };
+                let typ = ast::SimaType::Basic { kind: ast::BasicKind::Signed, width: 1 };
+                self.put(StackValue::Value(result.as_basic_value_enum(), typ), depth);
+            },
+            Duplicate => {
+                let val = self.take(depth);
+                self.put(val.clone(), depth);
+                self.put(val, depth);
+            },
+            Discard => {
+                self.take(depth);
+            },
+            Keep => {},
+            Exchange => {
+                let first = self.take(depth);
+                let second = self.take(depth);
+                self.put(first, depth);
+                self.put(second, depth);
+            },
+            Load => {
+                let (pointer, typ) = self.take_value(depth, span)?;
+                let target = pointee(&typ, span)?;
+                let val = self.builder.build_load(&pointer.into_pointer_value(), "");
+                self.put(StackValue::Value(val, target), depth);
+            },
+            Store => {
+                let (pointer, typ) = self.take_value(depth, span)?;
+                let target = pointee(&typ, span)?;
+                let value = self.take(depth);
+                let val = self.lower(value, Some(&target), span)?;
+                self.builder.build_store(&pointer.into_pointer_value(), &val);
+            },
+            Offset | Index => {
+                let (offset, offset_typ) = self.take_value(depth, span)?;
+                let (pointer, typ) = self.take_value(depth, span)?;
+                let target = pointee(&typ, span)?;
+                let offset = self.build_index(offset.into_int_value(), &offset_typ);
+                let moved = self.builder.build_gep(&pointer.into_pointer_value(), &[&offset], "");
+                if let Index = expr.kind {
+                    let val = self.builder.build_load(&moved, "");
+                    self.put(StackValue::Value(val, target), depth);
+                } else {
+                    self.put(StackValue::Value(moved.as_basic_value_enum(), typ), depth);
+                }
+            },
+            Field { ref record, ref field, access } => {
+                use source_ast::FieldAccess::*;
+                let (index, field_type) = self.field(record, field);
+                match access {
+                    Get => {
+                        let (val, _) = self.take_value(depth, span)?;
+                        let val = self.builder.build_extract_value(&val.into_struct_value(), index, "");
+                        self.put(StackValue::Value(val, field_type), depth);
+                    }
+                    Set => {
+                        let value = self.take(depth);
+                        let value = self.lower(value, Some(&field_type), span)?;
+                        let (val, typ) = self.take_value(depth, span)?;
+                        let val = self.builder.build_insert_value(&val.into_struct_value(), &value as &BasicValue, index, "");
+                        self.put(StackValue::Value(val, typ), depth);
+                    }
+                    Address => {
+                        let (pointer, _) = self.take_value(depth, span)?;
+                        let val = self.builder.build_struct_gep(&pointer.into_pointer_value(), index, "");
+                        let typ = ast::SimaType::Pointer { target: Box::new(field_type) };
+                        self.put(StackValue::Value(val.as_basic_value_enum(), typ), depth);
+                    }
+                }
+            },
+            Case {ref variant, ref case, ..} => {
+                // the case is put together in memory, where its payload can
+                // be written as the struct it is for that case
+                let (tag, payload) = self.case(variant, case);
+                let values = self.take_args(payload.len(), &payload, depth, span)?;
+                let variant_type = self.build_named_type(variant, span)?;
+                let slot = self.build_entry_alloca(&variant_type);
+                let tag_pointer = self.builder.build_struct_gep(&slot, 0, "");
+                self.builder.build_store(&tag_pointer, &self.context.i32_type().const_int(tag, false));
+                let payload_pointer = self.build_payload_pointer(slot, &payload, span)?;
+                for (index, val) in values.iter().enumerate() {
+                    let field = self.builder.build_struct_gep(&payload_pointer, index as u32, "");
+                    self.builder.build_store(&field, val);
+                }
+                let val = self.builder.build_load(&slot, "");
+                self.put(StackValue::Value(val, ast::SimaType::Named(variant.clone())), depth);
+            },
+            Match {ref variant, ref cases, ref out_ariety, ..} => {
+                let (val, _) = self.take_value(depth, span)?;
+                let variant_type = self.build_named_type(variant, span)?;
+                let slot = self.build_entry_alloca(&variant_type);
+                self.builder.build_store(&slot, &val);
+                let tag_pointer = self.builder.build_struct_gep(&slot, 0, "");
+                let tag = self.builder.build_load(&tag_pointer, "").into_int_value();
+
+                let function = self.current_function();
+                if cases.is_empty() {
+                    // a variant without cases has no values to match on
+                    self.builder.build_unreachable();
+                    let dead_block = self.context.append_basic_block(&function, "merge");
+                    self.builder.position_at_end(&dead_block);
+                    return Ok(());
+                }
+                let blocks: Vec<BasicBlock> = cases.iter()
+                    .map(|arm| self.context.append_basic_block(&function, &arm.case))
+                    .collect();
+                let merge_block = self.context.append_basic_block(&function, "merge");
+                let i32_type = self.context.i32_type();
+                let tags: Vec<IntValue> = cases.iter()
+                    .map(|arm| i32_type.const_int(self.case(variant, &arm.case).0, false))
+                    .collect();
+                // the tag is always one of the cases, so the last one needs
+                // no comparison
+                let last = cases.len() - 1;
+                let switch_cases: Vec<(&IntValue, &BasicBlock)> = tags.iter().zip(blocks.iter()).take(last).collect();
+                self.builder.build_switch(&tag, &blocks[last], &switch_cases);
+
+                let mut branches = Vec::new();
+                for (arm, block) in cases.iter().zip(blocks.iter()) {
+                    let saved_stack = self.stack.clone();
+                    self.builder.position_at_end(block);
+                    let (_, payload) = self.case(variant, &arm.case);
+                    let payload_pointer = self.build_payload_pointer(slot, &payload, span)?;
+                    for (index, typ) in payload.into_iter().enumerate() {
+                        let field = self.builder.build_struct_gep(&payload_pointer, index as u32, "");
+                        let val = self.builder.build_load(&field, "");
+                        self.put(StackValue::Value(val, typ), depth);
+                    }
+                    self.build_expression(&arm.body, depth)?;
+                    self.builder.build_unconditional_branch(&merge_block);
+                    let end_block = self.builder.get_insert_block().unwrap();
+                    branches.push((mem::replace(&mut self.stack, saved_stack), end_block));
+                }
+
+                self.builder.position_at_end(&merge_block);
+                self.stack = self.merge_stacks(branches, out_ariety.get(), depth, span)?;
+            },
+            Pack(ref typ) => {
+                let (element, length) = elements(typ, span)?;
+                let values = self.take_args(length as usize, &vec![element; length as usize], depth, span)?;
+                let val = match self.build_basic_type(typ, span)? {
+                    BasicTypeEnum::VectorType(t) => {
+                        let mut vector = t.get_undef();
+                        for (lane, val) in values.iter().enumerate() {
+                            let lane = self.context.i32_type().const_int(lane as u64, false);
+                            vector = self.builder.build_insert_element(&vector, val as &BasicValue, &lane, "");
+                        }
+                        vector.as_basic_value_enum()
+                    }
+                    t => {
+                        let mut array = t.into_array_type().get_undef();
+                        for (index, val) in values.iter().enumerate() {
+                            array = self.builder
+                                .build_insert_value(&array, val as &BasicValue, index as u32, "")
+                                .into_array_value();
+                        }
+                        array.as_basic_value_enum()
+                    }
+                };
+                self.put(StackValue::Value(val, typ.clone()), depth);
+            },
+            Extract => {
+                let (index, index_typ) = self.take_value(depth, span)?;
+                let (aggregate, typ) = self.take_value(depth, span)?;
+                let (element, _) = elements(&typ, span)?;
+                let val = match aggregate {
+                    BasicValueEnum::VectorValue(vector) =>
+                        self.builder.build_extract_element(&vector, &index.into_int_value(), ""),
+                    _ => {
+                        let (_, pointer) = self.build_element_pointer(aggregate, index.into_int_value(), &index_typ);
+                        self.builder.build_load(&pointer, "")
+                    }
+                };
+                self.put(StackValue::Value(val, element), depth);
+            },
+            Insert => {
+                let (index, index_typ) = self.take_value(depth, span)?;
+                let value = self.take(depth);
+                let (aggregate, typ) = self.take_value(depth, span)?;
+                let (element, _) = elements(&typ, span)?;
+                let value = self.lower(value, Some(&element), span)?;
+                let val = match aggregate {
+                    BasicValueEnum::VectorValue(vector) => self.builder
+                        .build_insert_element(&vector, &value, &index.into_int_value(), "")
+                        .as_basic_value_enum(),
+                    _ => {
+                        let (slot, pointer) = self.build_element_pointer(aggregate, index.into_int_value(), &index_typ);
+                        self.builder.build_store(&pointer, &value);
+                        self.builder.build_load(&slot, "")
+                    }
+                };
+                self.put(StackValue::Value(val, typ), depth);
+            },
+            Cast(ref target) => {
+                let (val, typ) = self.take_value(depth, span)?;
+                let result = self.build_cast(val, &typ, target, span)?;
+                self.put(StackValue::Value(result, target.clone()), depth);
+            },
+            SizeOf(ref typ) | AlignOf(ref typ) => {
+                let t = self.build_basic_type(typ, span)?;
+                let data = self.target_machine()?.get_data();
+                let bytes = match expr.kind {
+                    SizeOf(_) => data.get_abi_size(&t),
+                    _ => data.get_abi_alignment(&t) as u64,
+                };
+                let val = self.context.i64_type().const_int(bytes, false);
+                let typ = ast::SimaType::Basic { kind: ast::BasicKind::Unsigned, width: 64 };
+                self.put(StackValue::Value(val.as_basic_value_enum(), typ), depth);
+            },
+        }
+        Ok(())
+    }
+
+    fn build_arithmetic(&mut self, operator: ast::Operator, left: BasicValueEnum, right: BasicValueEnum, typ: &ast::SimaType) -> BasicValueEnum {
+        use source_ast::Operator::*;
+        if typ.is_float() {
+            let (left, right) = (left.into_float_value(), right.into_float_value());
+            match operator {
+                Add => self.builder.build_float_add(&left, &right, ""),
+                Subtract => self.builder.build_float_sub(&left, &right, ""),
+                Multiply => self.builder.build_float_mul(&left, &right, ""),
+                Divide => self.builder.build_float_div(&left, &right, ""),
+                Remainder => self.builder.build_float_rem(&left, &right, ""),
+            }.as_basic_value_enum()
+        } else {
+            let signed = typ.is_signed();
+            let (left, right) = (left.into_int_value(), right.into_int_value());
+            match operator {
+                Add => self.builder.build_int_add(&left, &right, ""),
+                Subtract => self.builder.build_int_sub(&left, &right, ""),
+                Multiply => self.builder.build_int_mul(&left, &right, ""),
+                Divide if signed => self.builder.build_int_signed_div(&left, &right, ""),
+                Divide => self.builder.build_int_unsigned_div(&left, &right, ""),
+                Remainder if signed => self.builder.build_int_signed_rem(&left, &right, ""),
+                Remainder => self.builder.build_int_unsigned_rem(&left, &right, ""),
+            }.as_basic_value_enum()
+        }
+    }
+
+    fn build_negate(&mut self, val: BasicValueEnum, typ: &ast::SimaType) -> BasicValueEnum {
+        if typ.is_float() {
+            self.builder.build_float_neg(&val.into_float_value(), "").as_basic_value_enum()
+        } else {
+            self.builder.build_int_neg(&val.into_int_value(), "").as_basic_value_enum()
+        }
+    }
+
+    /// Copies an array to the stack and returns where it is and a pointer to
+    /// its element at `index`. LLVM can only index array values with
+    /// constants, so `extract` and `insert` on arrays go through memory.
+    fn build_element_pointer(&mut self, array: BasicValueEnum, index: IntValue, index_typ: &ast::SimaType) -> (PointerValue, PointerValue) {
+        let slot = self.build_entry_alloca(&array.get_type());
+        self.builder.build_store(&slot, &array);
+        let index = self.build_index(index, index_typ);
+        let zero = self.context.i64_type().const_int(0, false);
+        let pointer = self.builder.build_gep(&slot, &[&zero, &index], "");
+        (slot, pointer)
+    }
+
+    /// Converts `val` from `from` to `to` with the instruction their kinds
+    /// call for: integers are extended by the signedness of the source.
+    fn build_cast(&mut self, val: BasicValueEnum, from: &ast::SimaType, to: &ast::SimaType, span: Span) -> Result<BasicValueEnum, Diagnostic> {
+        use source_ast::SimaType::{Basic, Pointer};
+        let target = self.build_basic_type(to, span)?;
+        Ok(match (val, from, target) {
+            (BasicValueEnum::IntValue(val), &Basic { width, .. }, BasicTypeEnum::IntType(t)) => {
+                let to_width = t.get_bit_width();
+                if to_width < width {
+                    self.builder.build_int_truncate(&val, &t, "").as_basic_value_enum()
+                } else if to_width == width {
+                    val.as_basic_value_enum()
+                } else if from.is_signed() && width > 1 {
+                    self.builder.build_int_s_extend(&val, &t, "").as_basic_value_enum()
+                } else {
+                    self.builder.build_int_z_extend(&val, &t, "").as_basic_value_enum()
+                }
+            }
+            (BasicValueEnum::IntValue(val), &Basic { width, .. }, BasicTypeEnum::FloatType(t)) => if from.is_signed() && width > 1 {
+                self.builder.build_signed_int_to_float(&val, &t, "").as_basic_value_enum()
+            } else {
+                self.builder.build_unsigned_int_to_float(&val, &t, "").as_basic_value_enum()
             },
-    		Duplicate => {
-    			let val = self.take(depth).clone();
-    			self.put(val, depth);
-       			self.put(val, depth);
-    		},
-    		Discard => {
-    			self.take(depth);
-    		},
-    		Keep => {},
-    		Exchange => {
-    			let first = self.take(depth);
-    			let second = self.take(depth);
-    			self.put(first, depth);
-    			self.put(second, depth);
-    		},
+            (BasicValueEnum::FloatValue(val), _, BasicTypeEnum::IntType(t)) => if to.is_signed() {
+                self.builder.build_float_to_signed_int(&val, &t, "").as_basic_value_enum()
+            } else {
+                self.builder.build_float_to_unsigned_int(&val, &t, "").as_basic_value_enum()
+            },
+            (BasicValueEnum::FloatValue(val), _, BasicTypeEnum::FloatType(t)) =>
+                self.builder.build_float_cast(&val, &t, "").as_basic_value_enum(),
+            (BasicValueEnum::PointerValue(val), &Pointer { .. }, BasicTypeEnum::PointerType(t)) =>
+                self.builder.build_pointer_cast(&val, &t, "").as_basic_value_enum(),
+            (BasicValueEnum::PointerValue(val), _, BasicTypeEnum::IntType(t)) =>
+                self.builder.build_ptr_to_int(&val, &t, "").as_basic_value_enum(),
+            (BasicValueEnum::IntValue(val), _, BasicTypeEnum::PointerType(t)) =>
+                self.builder.build_int_to_ptr(&val, &t, "").as_basic_value_enum(),
+            _ => return Err(Diagnostic::error(format!("Cannot cast {} to {}", from, to))
+                .with_primary(span, "invalid cast")),
+        })
+    }
+
+    /// Extends an offset for `+ptr` and `index` to 64 bits, so that unsigned
+    /// offsets are not taken for negative ones by the GEP.
+    fn build_index(&mut self, offset: IntValue, typ: &ast::SimaType) -> IntValue {
+        let i64_type = self.context.i64_type();
+        if offset.get_type().get_bit_width() >= 64 {
+            offset
+        } else if typ.is_signed() {
+            self.builder.build_int_s_extend(&offset, &i64_type, "")
+        } else {
+            self.builder.build_int_z_extend(&offset, &i64_type, "")
         }
     }
 
-    fn build_function_type(&mut self, typ: &ast::SimaType) -> FunctionType {
+    fn build_function_type(&mut self, typ: &ast::SimaType, variadic: bool, span: Span) -> Result<FunctionType, Diagnostic> {
         use source_ast::SimaType::*;
         use inkwell::types::BasicTypeEnum::*;
         use inkwell::types::BasicType;
@@ -146,52 +1141,252 @@ impl CodegenUnit {
             //A Day was lost to this Section
             let mut args_box: Vec<Box<BasicType>> = Vec::new();
             for t in args {
-                args_box.push(Box::new(self.build_basic_type(t)));
+                args_box.push(Box::new(self.build_basic_type(t, span)?));
             }
             let args_vec: Vec<&BasicType> = args_box.iter().map(|t| t.as_ref()).collect();
             let args: &[&BasicType] = args_vec.as_slice();
 
             if ret.is_empty() {
-                return self.context.void_type().fn_type(args, false);
-            }
-            match self.build_basic_type(&ret[0]) {
-                IntType(t) => t.fn_type(args, false),
-                FloatType(t) => t.fn_type(args, false),
-                PointerType(t) => t.fn_type(args, false),
-                StructType(t) => t.fn_type(args, false),
-                ArrayType(t) => t.fn_type(args, false),
-                VectorType(_) => panic!(),
+                return Ok(self.context.void_type().fn_type(args, variadic));
             }
+            let ret_type = if ret.len() == 1 {
+                self.build_basic_type(&ret[0], span)?
+            } else {
+                StructType(self.build_struct_type(ret, span)?)
+            };
+            Ok(match ret_type {
+                IntType(t) => t.fn_type(args, variadic),
+                FloatType(t) => t.fn_type(args, variadic),
+                PointerType(t) => t.fn_type(args, variadic),
+                StructType(t) => t.fn_type(args, variadic),
+                ArrayType(t) => t.fn_type(args, variadic),
+                VectorType(t) => t.fn_type(args, variadic),
+            })
         } else {
-            panic!()
+            Err(Diagnostic::error(format!("Words must have a function type, not {}", typ))
+                .with_primary(span, "declared here"))
+        }
+    }
+
+    /// The struct that the results of a word with several return values are
+    /// packed into.
+    fn build_struct_type(&mut self, types: &[ast::SimaType], span: Span) -> Result<StructType, Diagnostic> {
+        use inkwell::types::BasicType;
+
+        let mut fields: Vec<BasicTypeEnum> = Vec::with_capacity(types.len());
+        for t in types {
+            fields.push(self.build_basic_type(t, span)?);
         }
+        let fields: Vec<&BasicType> = fields.iter().map(|t| t as &BasicType).collect();
+        Ok(self.context.struct_type(&fields, false))
     }
 
-    fn build_basic_type(&mut self, typ: &ast::SimaType) -> BasicTypeEnum {
+    fn build_basic_type(&mut self, typ: &ast::SimaType, span: Span) -> Result<BasicTypeEnum, Diagnostic> {
         use source_ast::SimaType::*;
         use inkwell::types::BasicTypeEnum::*;
-        match *typ {
-            Function { .. } => panic!(),
+        Ok(match *typ {
+            Function { .. } => {
+                // quotations are passed around as function pointers
+                let t = self.build_function_type(typ, false, span)?
+                    .ptr_type(inkwell::AddressSpace::Generic);
+                BasicTypeEnum::PointerType(t)
+            }
             Pointer { ref target } => {
-                let target = self.build_basic_type(target);
+                let target = self.build_basic_type(target, span)?;
                 let address_space = inkwell::AddressSpace::Generic;
                 let t = match target {
                     PointerType(t) => t.ptr_type(address_space),
                     IntType(t) => t.ptr_type(address_space),
+                    FloatType(t) => t.ptr_type(address_space),
                     StructType(t) => t.ptr_type(address_space),
-                    _ => panic!(),
+                    ArrayType(t) => t.ptr_type(address_space),
+                    VectorType(t) => t.ptr_type(address_space),
                 };
                 BasicTypeEnum::PointerType(t)
             }
-            Basic { ref id } => {
-                let size: u32 = id[1..].parse().unwrap();
-                let t = self.context.custom_width_int_type(size);
+            Basic { kind: ast::BasicKind::Float, width } => {
+                // `Module::check_type` rejects any other width
+                let t = match width {
+                    16 => self.context.f16_type(),
+                    32 => self.context.f32_type(),
+                    64 => self.context.f64_type(),
+                    128 => self.context.f128_type(),
+                    _ => unreachable!(),
+                };
+                BasicTypeEnum::FloatType(t)
+            }
+            Basic { width, .. } => {
+                let t = self.context.custom_width_int_type(width);
                 BasicTypeEnum::IntType(t)
             }
             Opaque => {
-            	let o = self.opaque_type;
-            	BasicTypeEnum::StructType(o)
+                let o = self.opaque_type;
+                BasicTypeEnum::StructType(o)
+            }
+            Named(ref id) => BasicTypeEnum::StructType(self.build_named_type(id, span)?),
+            Array { ref element, length } => {
+                let t = match self.build_basic_type(element, span)? {
+                    IntType(t) => t.array_type(length),
+                    FloatType(t) => t.array_type(length),
+                    PointerType(t) => t.array_type(length),
+                    StructType(t) => t.array_type(length),
+                    ArrayType(t) => t.array_type(length),
+                    VectorType(t) => t.array_type(length),
+                };
+                BasicTypeEnum::ArrayType(t)
             }
+            Vector { ref element, length } => {
+                // `Module::check_type` only lets numbers into vectors
+                let t = match self.build_basic_type(element, span)? {
+                    IntType(t) => t.vec_type(length),
+                    FloatType(t) => t.vec_type(length),
+                    _ => unreachable!(),
+                };
+                BasicTypeEnum::VectorType(t)
+            }
+        })
+    }
+
+    /// The LLVM struct of a named type. Records get a body with their fields
+    /// in order and no packing, which gives them the layout C would.
+    /// Variants are an `i32` tag followed by storage that every case's
+    /// payload fits into, like a C struct of an enum and a union.
+    fn build_named_type(&mut self, id: &str, span: Span) -> Result<StructType, Diagnostic> {
+        use inkwell::types::BasicType;
+
+        if let Some(&t) = self.named_types.get(id) {
+            return Ok(t);
         }
+        // the struct is known before its fields are built, so that a record
+        // can point to itself
+        let t = self.context.opaque_struct_type(id);
+        self.named_types.insert(id.to_string(), t);
+        match self.types[id].definition.clone() {
+            ast::TypeDefinition::Record { ref fields } => {
+                let mut field_types: Vec<BasicTypeEnum> = Vec::with_capacity(fields.len());
+                for &(_, ref field) in fields {
+                    field_types.push(self.build_basic_type(field, span)?);
+                }
+                let field_types: Vec<&BasicType> = field_types.iter().map(|t| t as &BasicType).collect();
+                t.set_body(&field_types, false);
+            }
+            ast::TypeDefinition::Variant { ref cases } => {
+                let mut payloads = Vec::with_capacity(cases.len());
+                for &(_, ref payload) in cases {
+                    payloads.push(self.build_struct_type(payload, span)?);
+                }
+                let data = self.target_machine()?.get_data();
+                let size = payloads.iter().map(|p| data.get_abi_size(p)).max().unwrap_or(0);
+                // the storage starts with the payload of the most aligned
+                // case, which gives it that alignment whatever the case
+                // holds, and is padded with bytes to the largest case
+                let storage = match payloads.iter().max_by_key(|p| data.get_abi_alignment(*p)) {
+                    Some(aligned) => {
+                        let padding = self.context.i8_type().array_type((size - data.get_abi_size(aligned)) as u32);
+                        self.context.struct_type(&[aligned as &BasicType, &padding as &BasicType], false)
+                    }
+                    None => self.context.struct_type(&[], false),
+                };
+                let tag = self.context.i32_type();
+                t.set_body(&[&tag as &BasicType, &storage as &BasicType], false);
+            }
+            ast::TypeDefinition::Opaque => {}
+        }
+        Ok(t)
+    }
+
+    /// The index and type of a field of a record.
+    fn field(&self, record: &str, field: &str) -> (u32, ast::SimaType) {
+        let (index, typ) = self.types[record].definition.field(field).unwrap();
+        (index as u32, typ.clone())
+    }
+
+    /// The tag and payload types of a case of a variant.
+    fn case(&self, variant: &str, case: &str) -> (u64, Vec<ast::SimaType>) {
+        let (tag, payload) = self.types[variant].definition.case(case).unwrap();
+        (tag as u64, payload.to_vec())
     }
+
+    /// A pointer to the payload of the variant at `slot`, as the struct of
+    /// the payload of one case.
+    fn build_payload_pointer(&mut self, slot: PointerValue, payload: &[ast::SimaType], span: Span) -> Result<PointerValue, Diagnostic> {
+        let case_type = self.build_struct_type(payload, span)?;
+        let storage = self.builder.build_struct_gep(&slot, 1, "");
+        let case_pointer = case_type.ptr_type(inkwell::AddressSpace::Generic);
+        Ok(self.builder.build_pointer_cast(&storage, &case_pointer, ""))
+    }
+
+    /// Stack memory for a value of type `typ`. It is allocated in the entry
+    /// block, so that code in a loop reuses it instead of growing the stack.
+    fn build_entry_alloca<T: inkwell::types::BasicType>(&mut self, typ: &T) -> PointerValue {
+        let entry = self.current_function().get_entry_basic_block().unwrap();
+        let builder = self.context.create_builder();
+        match entry.get_first_instruction() {
+            Some(first) => builder.position_before(&first),
+            None => builder.position_at_end(&entry),
+        }
+        builder.build_alloca(typ, "")
+    }
+}
+
+/// The type a pointer on the stack points to.
+fn pointee(typ: &ast::SimaType, span: Span) -> Result<ast::SimaType, Diagnostic> {
+    match *typ {
+        ast::SimaType::Pointer { ref target } => Ok((**target).clone()),
+        _ => Err(Diagnostic::error(format!("Expected a pointer, found {}", typ))
+            .with_primary(span, "expects a pointer")),
+    }
+}
+
+/// The element type and length of an array or vector on the stack.
+fn elements(typ: &ast::SimaType, span: Span) -> Result<(ast::SimaType, u32), Diagnostic> {
+    match typ.elements() {
+        Some((element, length)) => Ok((element.clone(), length)),
+        None => Err(Diagnostic::error(format!("Expected an array or a vector, found {}", typ))
+            .with_primary(span, "expects an array or a vector")),
+    }
+}
+
+/// The type C gives a function declared with `typ`: arrays are passed as a
+/// pointer to their first element and cannot be returned. C functions
+/// return at most one value, so the struct that several results are
+/// returned in would not match how they actually return. Records and
+/// variants would be passed as LLVM structs, which is not how C passes
+/// structs, so they have to be passed by pointer.
+fn c_signature(typ: &ast::SimaType, span: Span) -> Result<ast::SimaType, Diagnostic> {
+    let (args, ret) = match *typ {
+        ast::SimaType::Function { ref args, ref ret } => (args.clone(), ret.clone()),
+        _ => return Ok(typ.clone()),
+    };
+    if ret.len() > 1 {
+        return Err(Diagnostic::error(format!("C functions cannot return {} values", ret.len()))
+            .with_primary(span, "declared here"));
+    }
+    if let Some(named) = args.iter().chain(&ret).find(|t| match **t { ast::SimaType::Named(_) => true, _ => false }) {
+        return Err(Diagnostic::error(format!("C functions cannot take or return {} by value", named))
+            .with_primary(span, "declared here"));
+    }
+    if let Some(vector) = args.iter().chain(&ret).find(|t| !t.is_c_compatible()) {
+        return Err(Diagnostic::error(format!("C functions cannot take or return {}", vector))
+            .with_primary(span, "only vectors of 16 bytes with a power of two elements can be passed to C"));
+    }
+    if let Some(array) = ret.iter().find(|t| match **t { ast::SimaType::Array { .. } => true, _ => false }) {
+        return Err(Diagnostic::error(format!("C functions cannot return {}", array))
+            .with_primary(span, "declared here"));
+    }
+    let args = args.into_iter().map(|t| match t {
+        ast::SimaType::Array { element, .. } => ast::SimaType::Pointer { target: element },
+        t => t,
+    }).collect();
+    Ok(ast::SimaType::Function { args, ret })
+}
+
+fn carried_quotation(span: Span) -> Diagnostic {
+    Diagnostic::error("Quotations cannot be carried through loops")
+        .with_primary(span, "a quotation would stay on the stack across iterations")
+}
+
+fn operand_mismatch<W: ::std::fmt::Display>(word: &W, left: &ast::SimaType, right: &ast::SimaType, span: Span) -> Diagnostic {
+    Diagnostic::error(format!("Operands of '{}' have different types", word))
+        .with_primary(span, format!("{} and {}", left, right))
 }
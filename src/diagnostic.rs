@@ -0,0 +1,95 @@
+use std::fmt;
+
+/// A range of bytes in the source text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span{
+    pub start: usize,
+    pub end: usize,
+}
+impl Span{
+    pub fn new(start: usize, end: usize) -> Span{
+        Span{start, end}
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity{
+    Error,
+}
+impl fmt::Display for Severity{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+        f.write_str(match *self{
+            Severity::Error => "error",
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Label{
+    pub span: Span,
+    pub message: String,
+}
+
+/// A message about the source text. The primary label points at the code
+/// the message is about, secondary labels at code that explains it.
+#[derive(Debug, Clone)]
+pub struct Diagnostic{
+    pub severity: Severity,
+    pub message: String,
+    pub primary: Option<Label>,
+    pub secondary: Vec<Label>,
+}
+
+impl Diagnostic{
+    pub fn error<S: Into<String>>(message: S) -> Diagnostic{
+        Diagnostic{
+            severity: Severity::Error,
+            message: message.into(),
+            primary: None,
+            secondary: Vec::new(),
+        }
+    }
+    pub fn with_primary<S: Into<String>>(mut self, span: Span, message: S) -> Diagnostic{
+        self.primary = Some(Label{span, message: message.into()});
+        self
+    }
+    pub fn with_secondary<S: Into<String>>(mut self, span: Span, message: S) -> Diagnostic{
+        self.secondary.push(Label{span, message: message.into()});
+        self
+    }
+
+    /// Renders the diagnostic with the labelled lines of `source`, the
+    /// primary label underlined with `^` and secondary ones with `-`.
+    /// Columns count characters, and the tabs before a label are repeated
+    /// under it, so that the underline lines up however wide a tab is.
+    pub fn render(&self, filename: &str, source: &str) -> String{
+        let mut out = format!("{}: {}\n", self.severity, self.message);
+        let labels = self.primary.iter().map(|l| (l, '^'))
+            .chain(self.secondary.iter().map(|l| (l, '-')));
+        for (label, underline) in labels{
+            let (line, column) = line_column(source, label.span.start);
+            let text = source.lines().nth(line).unwrap_or("");
+            let gutter = (line + 1).to_string();
+            let pad = " ".repeat(gutter.len());
+            let before = &text[..column.min(text.len())];
+            // spans that run over several lines are only underlined on the first
+            let end = (column + label.span.end.saturating_sub(label.span.start)).min(text.len());
+            let width = text.get(before.len()..end).map_or(0, |t| t.chars().count()).max(1);
+            let indent: String = before.chars().map(|c| if c == '\t' { '\t' } else { ' ' }).collect();
+            out += &format!("{}--> {}:{}:{}\n", pad, filename, line + 1, before.chars().count() + 1);
+            out += &format!("{} |\n", pad);
+            out += &format!("{} | {}\n", gutter, text);
+            out += &format!("{} | {}{} {}\n", pad, indent,
+                underline.to_string().repeat(width), label.message);
+        }
+        out
+    }
+}
+
+/// The zero based line and column of a byte offset.
+fn line_column(source: &str, offset: usize) -> (usize, usize){
+    let before = &source[..offset.min(source.len())];
+    let line = before.matches('\n').count();
+    let column = before.len() - before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    (line, column)
+}
@@ -0,0 +1,932 @@
+use std::cmp;
+use std::collections::HashMap;
+use std::mem;
+use std::ptr;
+use diagnostic::{Diagnostic, Span};
+use source_ast::{Module, Expression, SimaType, BasicKind, Operator, Comparison, TypeDefinition, FieldAccess};
+
+/// Runs `main` of a module that passed `Module::calc_arieties` and the type
+/// checker, without going through LLVM. Returns what `main` returned.
+pub fn run(module: &Module) -> Result<i32, Diagnostic> {
+    let main = match module.functions.get("main") {
+        Some(main) => main,
+        None => return Err(Diagnostic::error("The module has no 'main' word to run")),
+    };
+    let mut interpreter = Interpreter::new(module);
+    interpreter.call("main", 0, main.span, 0)?;
+    match interpreter.stack.pop() {
+        Some(Value::Integer{bits, ..}) => Ok(bits as i32),
+        _ => Err(Diagnostic::error("'main' has to return an integer to be run")
+            .with_primary(main.span, format!("declared as {}", main.typ))),
+    }
+}
+
+/// A value on the stack of the interpreter.
+#[derive(Debug, Clone)]
+pub enum Value {
+    /// An integer of the given kind and width. The bits above the width are
+    /// always zero.
+    Integer{bits: u64, kind: BasicKind, width: u32},
+    Float{value: f64, width: u32},
+    /// An address in the memory of the interpreter's process, so that it can
+    /// be handed to C functions, and the type it points to.
+    Pointer{address: usize, target: SimaType},
+    Quotation(Expression),
+    /// The fields of a record, in the order they are declared.
+    Record(Vec<Value>),
+    /// A case of a variant, by its index among the cases, and its payload.
+    Variant{tag: usize, payload: Vec<Value>},
+    /// The elements of an array or a vector.
+    Array(Vec<Value>),
+}
+
+impl Value {
+    fn boolean(value: bool) -> Value {
+        Value::Integer{bits: value as u64, kind: BasicKind::Signed, width: 1}
+    }
+
+    /// A float of the given width, rounded to `f32` if it is one.
+    fn float(value: f64, width: u32) -> Value {
+        match width {
+            32 => Value::Float{value: value as f32 as f64, width},
+            _ => Value::Float{value, width},
+        }
+    }
+
+    /// Turns a raw integer, pointer or float that came back from C or was
+    /// read from memory into a value of the given type.
+    fn from_raw(raw: u64, typ: &SimaType) -> Value {
+        match *typ {
+            SimaType::Basic{kind: BasicKind::Float, width: 32} => Value::Float{value: f32::from_bits(raw as u32) as f64, width: 32},
+            SimaType::Basic{kind: BasicKind::Float, width} => Value::Float{value: f64::from_bits(raw), width},
+            SimaType::Basic{kind, width} => Value::Integer{bits: truncate(raw, width), kind, width},
+            SimaType::Pointer{ref target} => Value::Pointer{address: raw as usize, target: (**target).clone()},
+            _ => Value::Pointer{address: raw as usize, target: SimaType::Opaque},
+        }
+    }
+
+    /// The bits C would see for this value. Integers are extended to 64 bits
+    /// according to their kind.
+    fn to_raw(&self) -> u64 {
+        match *self {
+            Value::Integer{bits, kind, width} => signed_value(bits, kind, width) as u64,
+            Value::Float{value, width: 32} => (value as f32).to_bits() as u64,
+            Value::Float{value, ..} => value.to_bits(),
+            Value::Pointer{address, ..} => address as u64,
+            Value::Quotation(_) | Value::Record(_) | Value::Variant{..} | Value::Array(_) => 0,
+        }
+    }
+}
+
+/// Evaluates expressions with the same stack discipline as `CodegenUnit`:
+/// words work at a depth below the top of the stack, which is how `,` lets
+/// its left side run below its right side.
+pub struct Interpreter<'a> {
+    module: &'a Module,
+    stack: Vec<Value>,
+    /// The string literals handed out so far, by where they are in the
+    /// source.
+    strings: HashMap<usize, Vec<u8>>,
+}
+
+impl<'a> Interpreter<'a> {
+    pub fn new(module: &'a Module) -> Interpreter<'a> {
+        Interpreter {
+            module,
+            stack: Vec::new(),
+            strings: HashMap::new(),
+        }
+    }
+
+    /// Runs `expr` on top of `stack` and returns the stack it leaves.
+    pub fn evaluate(&mut self, stack: Vec<Value>, expr: &Expression) -> Result<Vec<Value>, Diagnostic> {
+        let saved_stack = mem::replace(&mut self.stack, stack);
+        let result = self.eval(expr, 0);
+        let stack = mem::replace(&mut self.stack, saved_stack);
+        result.map(|_| stack)
+    }
+
+    fn put(&mut self, value: Value, depth: usize) {
+        let index = self.stack.len() - depth;
+        self.stack.insert(index, value);
+    }
+    fn take(&mut self, depth: usize) -> Value {
+        let index = self.stack.len() - depth - 1;
+        self.stack.remove(index)
+    }
+    /// Takes `count` values, returning them in the order they were pushed.
+    fn take_n(&mut self, count: usize, depth: usize) -> Vec<Value> {
+        let end = self.stack.len() - depth;
+        self.stack.drain(end - count..end).collect()
+    }
+    fn take_pointer(&mut self, depth: usize, span: Span) -> Result<(usize, SimaType), Diagnostic> {
+        match self.take(depth) {
+            Value::Pointer{address, target} => Ok((address, target)),
+            _ => Err(Diagnostic::error("Expected a pointer")
+                .with_primary(span, "expects a pointer on the stack")),
+        }
+    }
+    /// The address of the bytes of the string literal at `span`, which are
+    /// kept as long as the interpreter, like globals would be.
+    fn string(&mut self, span: Span, bytes: &[u8], terminated: bool) -> usize {
+        self.strings.entry(span.start).or_insert_with(|| {
+            let mut bytes = bytes.to_vec();
+            if terminated {
+                bytes.push(0);
+            }
+            bytes
+        }).as_ptr() as usize
+    }
+
+    /// Calls a word. Defined words run on a fresh stack holding just their
+    /// arguments, like the functions `CodegenUnit` builds for them; words
+    /// that are only declared are looked up among the supported C functions.
+    /// `extra` is the number of extra arguments of a variadic call. Records
+    /// are built from their fields when their name is called.
+    fn call(&mut self, id: &str, extra: usize, span: Span, depth: usize) -> Result<(), Diagnostic> {
+        let module = self.module;
+        let function = match module.functions.get(id) {
+            Some(function) => function,
+            None => {
+                let fields = record_fields(module, id);
+                let values = self.take_n(fields.len(), depth);
+                self.put(Value::Record(values), depth);
+                return Ok(());
+            },
+        };
+        let args = self.take_n(function.in_ariety() + extra, depth);
+        let results = match function.expr {
+            Some(ref expr) => self.evaluate(args, expr)?,
+            None => {
+                let ret_types = match function.typ {
+                    SimaType::Function{ref ret, ..} => ret.clone(),
+                    _ => Vec::new(),
+                };
+                // a float would come back in another register than the
+                // raw bits of the result
+                if ret_types.iter().any(SimaType::is_float) {
+                    return Err(Diagnostic::error(format!("The interpreter cannot take floats returned by '{}'", id))
+                        .with_primary(span, "returns a float"));
+                }
+                let raw = call_external(id, &args, span)?;
+                raw.into_iter().zip(ret_types.iter()).map(|(raw, typ)| Value::from_raw(raw, typ)).collect()
+            },
+        };
+        for value in results {
+            self.put(value, depth);
+        }
+        Ok(())
+    }
+
+    /// Runs a quotation from the stack.
+    fn apply(&mut self, quotation: Value, depth: usize, span: Span) -> Result<(), Diagnostic> {
+        match quotation {
+            Value::Quotation(inner) => self.eval(&inner, depth),
+            _ => Err(Diagnostic::error("Only quotations can be called")
+                .with_primary(span, "expects a quotation on the stack")),
+        }
+    }
+
+    fn condition(&mut self, depth: usize, span: Span) -> Result<bool, Diagnostic> {
+        match self.take(depth) {
+            Value::Integer{bits, ..} => Ok(bits != 0),
+            _ => Err(Diagnostic::error("Conditions have to be integers")
+                .with_primary(span, "expects an integer on the stack")),
+        }
+    }
+
+    fn eval(&mut self, expr: &Expression, depth: usize) -> Result<(), Diagnostic> {
+        use source_ast::ExpressionKind::*;
+
+        let span = expr.span;
+        match expr.kind {
+            Concat{ref left, ref right} => {
+                self.eval(left, depth)?;
+                self.eval(right, depth)?;
+            },
+            Sidecat{ref left, ref right} => {
+                self.eval(left, depth + right.in_ariety())?;
+                self.eval(right, depth)?;
+            },
+            Block{ref inner} => {
+                self.put(Value::Quotation((**inner).clone()), depth);
+            },
+            StringLiteral{ref bytes, ..} => {
+                let address = self.string(span, bytes, true);
+                self.put(Value::Pointer{address, target: SimaType::Basic{kind: BasicKind::Signed, width: 8}}, depth);
+            },
+            ByteString{ref bytes, ..} => {
+                let address = self.string(span, bytes, false);
+                self.put(Value::Pointer{address, target: SimaType::Basic{kind: BasicKind::Signed, width: 8}}, depth);
+                self.put(Value::Integer{bits: bytes.len() as u64, kind: BasicKind::Unsigned, width: 64}, depth);
+            },
+            Number{value, ref typ, ..} => {
+                let (kind, width) = typ.get().unwrap();
+                if width > 64 {
+                    return Err(unsupported_width(width, span));
+                }
+                let bits = truncate(value.unwrap() as u64, width);
+                self.put(Value::Integer{bits, kind, width}, depth);
+            },
+            Float{ref literal, ref width} => {
+                self.put(Value::float(literal.parse().unwrap(), width.get().unwrap_or(64)), depth);
+            },
+            Identifier{ref id, extra, ..} => self.call(id, extra.unwrap_or(0), span, depth)?,
+            Apply{..} => {
+                let quotation = self.take(depth);
+                self.apply(quotation, depth, span)?;
+            },
+            Conditional{..} => {
+                let otherwise = self.take(depth);
+                let then = self.take(depth);
+                let quotation = if self.condition(depth, span)? { then } else { otherwise };
+                self.apply(quotation, depth, span)?;
+            },
+            While{..} => {
+                let body = self.take(depth);
+                let condition = self.take(depth);
+                loop {
+                    self.apply(condition.clone(), depth, span)?;
+                    if !self.condition(depth, span)? {
+                        break;
+                    }
+                    self.apply(body.clone(), depth, span)?;
+                }
+            },
+            Times{ref body} => {
+                let quotation = self.take(depth);
+                let count = match self.take(depth + body.get().in_ariety) {
+                    Value::Integer{bits, kind: BasicKind::Unsigned, ..} => bits,
+                    Value::Integer{bits, kind, width} => signed_value(bits, kind, width).max(0) as u64,
+                    _ => return Err(Diagnostic::error("'times' expects an integer count")
+                        .with_primary(span, "expects an integer below the quotation")),
+                };
+                for _ in 0..count {
+                    self.apply(quotation.clone(), depth, span)?;
+                }
+            },
+            Arithmetic(operator) => {
+                let right = self.take(depth);
+                let left = self.take(depth);
+                let result = arithmetic(operator, left, right, span)?;
+                self.put(result, depth);
+            },
+            Negate => {
+                let value = self.take(depth);
+                let result = negate(value, span)?;
+                self.put(result, depth);
+            },
+            Comparison(comparison) => {
+                let right = self.take(depth);
+                let left = self.take(depth);
+                let result = compare(comparison, left, right, span)?;
+                self.put(Value::boolean(result), depth);
+            },
+            Duplicate => {
+                let value = self.take(depth);
+                self.put(value.clone(), depth);
+                self.put(value, depth);
+            },
+            Discard => {
+                self.take(depth);
+            },
+            Keep => {},
+            Exchange => {
+                let first = self.take(depth);
+                let second = self.take(depth);
+                self.put(first, depth);
+                self.put(second, depth);
+            },
+            Load => {
+                let (address, target) = self.take_pointer(depth, span)?;
+                let value = load(self.module, address, &target, span)?;
+                self.put(value, depth);
+            },
+            Store => {
+                let (address, target) = self.take_pointer(depth, span)?;
+                let value = self.take(depth);
+                store(self.module, address, &target, value, span)?;
+            },
+            Offset | Index => {
+                let offset = match self.take(depth) {
+                    Value::Integer{bits, kind, width} => signed_value(bits, kind, width),
+                    _ => return Err(Diagnostic::error(format!("'{}' expects an integer offset", expr))
+                        .with_primary(span, "expects an integer on top of the stack")),
+                };
+                let (address, target) = self.take_pointer(depth, span)?;
+                let (size, _) = layout(self.module, &target, span)?;
+                let address = (address as i64).wrapping_add(offset.wrapping_mul(size as i64)) as usize;
+                let value = match expr.kind {
+                    Index => load(self.module, address, &target, span)?,
+                    _ => Value::Pointer{address, target},
+                };
+                self.put(value, depth);
+            },
+            Field{ref record, ref field, access} => {
+                let module = self.module;
+                let (index, typ) = module.types[record].definition.field(field).unwrap();
+                match access {
+                    FieldAccess::Get => match self.take(depth) {
+                        Value::Record(mut values) => self.put(values.swap_remove(index), depth),
+                        _ => return Err(expected_record(record, span)),
+                    },
+                    FieldAccess::Set => {
+                        let value = self.take(depth);
+                        match self.take(depth) {
+                            Value::Record(mut values) => {
+                                values[index] = value;
+                                self.put(Value::Record(values), depth);
+                            },
+                            _ => return Err(expected_record(record, span)),
+                        }
+                    },
+                    FieldAccess::Address => {
+                        let (address, _) = self.take_pointer(depth, span)?;
+                        let (offsets, _, _) = record_layout(module, record, span)?;
+                        let address = address + offsets[index] as usize;
+                        self.put(Value::Pointer{address, target: typ.clone()}, depth);
+                    },
+                }
+            },
+            Case{ref variant, ref case, ref payload} => {
+                let (tag, _) = self.module.types[variant].definition.case(case).unwrap();
+                let payload = self.take_n(payload.get(), depth);
+                self.put(Value::Variant{tag, payload}, depth);
+            },
+            Match{ref variant, ref cases, ..} => {
+                let module = self.module;
+                let (tag, payload) = match self.take(depth) {
+                    Value::Variant{tag, payload} => (tag, payload),
+                    _ => return Err(Diagnostic::error(format!("Expected a {} variant", variant))
+                        .with_primary(span, "expects a variant on the stack")),
+                };
+                let case = &variant_cases(module, variant).unwrap()[tag].0;
+                let arm = cases.iter().find(|arm| arm.case == *case).unwrap();
+                for value in payload {
+                    self.put(value, depth);
+                }
+                self.eval(&arm.body, depth)?;
+            },
+            Pack(ref typ) => {
+                let (_, length) = typ.elements().unwrap();
+                let values = self.take_n(length as usize, depth);
+                self.put(Value::Array(values), depth);
+            },
+            Extract => {
+                let index = self.take(depth);
+                match self.take(depth) {
+                    Value::Array(mut values) => {
+                        let index = element_index(index, values.len(), span)?;
+                        self.put(values.swap_remove(index), depth);
+                    },
+                    _ => return Err(expected_array(expr, span)),
+                }
+            },
+            Insert => {
+                let index = self.take(depth);
+                let value = self.take(depth);
+                match self.take(depth) {
+                    Value::Array(mut values) => {
+                        let index = element_index(index, values.len(), span)?;
+                        values[index] = value;
+                        self.put(Value::Array(values), depth);
+                    },
+                    _ => return Err(expected_array(expr, span)),
+                }
+            },
+            Cast(ref target) => {
+                let value = self.take(depth);
+                let result = cast(value, target, span)?;
+                self.put(result, depth);
+            },
+            SizeOf(ref typ) | AlignOf(ref typ) => {
+                let (size, align) = layout(self.module, typ, span)?;
+                let bits = match expr.kind {
+                    SizeOf(_) => size,
+                    _ => align,
+                };
+                self.put(Value::Integer{bits, kind: BasicKind::Unsigned, width: 64}, depth);
+            },
+        }
+        Ok(())
+    }
+}
+
+/// Clears the bits of `bits` above `width`.
+fn truncate(bits: u64, width: u32) -> u64 {
+    if width >= 64 {
+        bits
+    } else {
+        bits & ((1 << width) - 1)
+    }
+}
+
+/// The integer as an `i64`, reading it as signed if its kind is.
+fn signed_value(bits: u64, kind: BasicKind, width: u32) -> i64 {
+    if kind == BasicKind::Signed && width > 0 && width < 64 {
+        let shift = 64 - width;
+        ((bits << shift) as i64) >> shift
+    } else {
+        bits as i64
+    }
+}
+
+/// The size and alignment of a type in bytes. The interpreter's own process
+/// is the target, which is assumed to be a 64 bit one.
+fn layout(module: &Module, typ: &SimaType, span: Span) -> Result<(u64, u64), Diagnostic> {
+    match *typ {
+        SimaType::Basic{width, ..} if width > 64 => Err(unsupported_width(width, span)),
+        SimaType::Basic{width, ..} => {
+            let bytes = cmp::max(width.next_power_of_two() / 8, 1) as u64;
+            Ok((bytes, bytes))
+        },
+        SimaType::Pointer{..} | SimaType::Function{..} => {
+            let bytes = mem::size_of::<usize>() as u64;
+            Ok((bytes, bytes))
+        },
+        SimaType::Named(ref id) if is_record(module, id) => {
+            let (_, size, align) = record_layout(module, id, span)?;
+            Ok((size, align))
+        },
+        SimaType::Named(ref id) if variant_cases(module, id).is_some() => {
+            let (_, size, align) = variant_layout(module, id, span)?;
+            Ok((size, align))
+        },
+        SimaType::Array{ref element, length} => {
+            let (size, align) = layout(module, element, span)?;
+            Ok((size * length as u64, align))
+        },
+        // LLVM packs the elements of vectors bit by bit, and aligns vectors
+        // to their size rounded up to a power of two
+        SimaType::Vector{ref element, length} => {
+            layout(module, element, span)?;
+            let bits = lane_width(element) as u64 * length as u64;
+            let bytes = cmp::max((bits + 7) / 8, 1);
+            let align = bytes.next_power_of_two();
+            Ok((round_up(bytes, align), align))
+        },
+        SimaType::Opaque | SimaType::Named(_) => Err(Diagnostic::error("Opaque values have no size")
+            .with_primary(span, "needs the size of an opaque type")),
+    }
+}
+
+fn is_record(module: &Module, id: &str) -> bool {
+    match module.types[id].definition {
+        TypeDefinition::Record{..} => true,
+        _ => false,
+    }
+}
+
+/// The fields of the record `id`, which the checker made sure is one.
+fn record_fields<'m>(module: &'m Module, id: &str) -> &'m [(String, SimaType)] {
+    match module.types[id].definition {
+        TypeDefinition::Record{ref fields} => fields,
+        _ => panic!("'{}' is not a record", id),
+    }
+}
+
+/// The cases of `id`, if it is a variant.
+fn variant_cases<'m>(module: &'m Module, id: &str) -> Option<&'m [(String, Vec<SimaType>)]> {
+    match module.types[id].definition {
+        TypeDefinition::Variant{ref cases} => Some(cases),
+        _ => None,
+    }
+}
+
+/// The offsets of the fields of a record and its size and alignment.
+fn record_layout(module: &Module, id: &str, span: Span) -> Result<(Vec<u64>, u64, u64), Diagnostic> {
+    struct_layout(module, record_fields(module, id).iter().map(|&(_, ref t)| t), span)
+}
+
+/// The offsets of values of the given types and the size and alignment of
+/// all of them together, laid out the way C lays out structs.
+fn struct_layout<'t, I: IntoIterator<Item = &'t SimaType>>(module: &Module, types: I, span: Span) -> Result<(Vec<u64>, u64, u64), Diagnostic> {
+    let mut offsets = Vec::new();
+    let mut size = 0;
+    let mut align = 1;
+    for typ in types {
+        let (field_size, field_align) = layout(module, typ, span)?;
+        size = round_up(size, field_align);
+        offsets.push(size);
+        size += field_size;
+        align = cmp::max(align, field_align);
+    }
+    Ok((offsets, round_up(size, align), align))
+}
+
+/// The offset of the payload of a variant and its size and alignment. The
+/// tag is a `u32`, and the payload starts at the alignment of the most
+/// aligned case, as in `CodegenUnit::build_named_type`.
+fn variant_layout(module: &Module, id: &str, span: Span) -> Result<(u64, u64, u64), Diagnostic> {
+    let mut payload_size = 0;
+    let mut payload_align = 1;
+    for &(_, ref payload) in variant_cases(module, id).unwrap() {
+        let (_, size, align) = struct_layout(module, payload, span)?;
+        payload_size = cmp::max(payload_size, size);
+        payload_align = cmp::max(payload_align, align);
+    }
+    let offset = round_up(4, payload_align);
+    let align = cmp::max(4, payload_align);
+    Ok((offset, round_up(offset + payload_size, align), align))
+}
+
+fn round_up(size: u64, align: u64) -> u64 {
+    (size + align - 1) / align * align
+}
+
+fn expected_record(record: &str, span: Span) -> Diagnostic {
+    Diagnostic::error(format!("Expected a {} record", record))
+        .with_primary(span, "expects a record on the stack")
+}
+
+fn expected_array(word: &Expression, span: Span) -> Diagnostic {
+    Diagnostic::error(format!("'{}' expects an array or a vector", word))
+        .with_primary(span, "expects an array or a vector below the index")
+}
+
+/// Checks that an index of `extract` or `insert` is within the `length`
+/// elements of an array or vector.
+fn element_index(index: Value, length: usize, span: Span) -> Result<usize, Diagnostic> {
+    let index = match index {
+        Value::Integer{bits, kind, width} => signed_value(bits, kind, width),
+        _ => return Err(Diagnostic::error("Indices have to be integers")
+            .with_primary(span, "expects an integer on top of the stack")),
+    };
+    if index < 0 || index as u64 >= length as u64 {
+        return Err(Diagnostic::error(format!("Index {} is out of bounds for {} elements", index, length))
+            .with_primary(span, "indexes out of bounds"));
+    }
+    Ok(index as usize)
+}
+
+/// Converts a number or pointer the way `CodegenUnit::build_cast` does.
+fn cast(value: Value, to: &SimaType, span: Span) -> Result<Value, Diagnostic> {
+    Ok(match (value, to) {
+        (_, &SimaType::Basic{width, ..}) if width > 64 => return Err(unsupported_width(width, span)),
+        (Value::Integer{bits, kind, width: from}, &SimaType::Basic{kind: BasicKind::Float, width}) => {
+            let value = match kind {
+                BasicKind::Signed if from > 1 => signed_value(bits, kind, from) as f64,
+                _ => bits as f64,
+            };
+            Value::float(value, width)
+        },
+        (Value::Integer{bits, kind, width: from}, &SimaType::Basic{..}) => {
+            let extended = match kind {
+                BasicKind::Signed if from > 1 => signed_value(bits, kind, from) as u64,
+                _ => bits,
+            };
+            Value::from_raw(extended, to)
+        },
+        (Value::Float{value, ..}, &SimaType::Basic{kind: BasicKind::Float, width}) => Value::float(value, width),
+        (Value::Float{value, ..}, &SimaType::Basic{kind: BasicKind::Signed, ..}) => Value::from_raw(value as i64 as u64, to),
+        (Value::Float{value, ..}, &SimaType::Basic{..}) => Value::from_raw(value as u64, to),
+        (Value::Pointer{address, ..}, _) => Value::from_raw(address as u64, to),
+        (Value::Integer{bits, ..}, &SimaType::Pointer{..}) => Value::from_raw(bits, to),
+        _ => return Err(Diagnostic::error(format!("Cannot cast to {}", to))
+            .with_primary(span, "invalid cast")),
+    })
+}
+
+/// Reads a value of type `typ`. Values are read and written byte by byte
+/// from the low end of their raw bits, as on a little endian machine,
+/// records field by field, variants as their tag and payload and arrays and
+/// vectors element by element.
+fn load(module: &Module, address: usize, typ: &SimaType, span: Span) -> Result<Value, Diagnostic> {
+    if let Some(width) = packed(typ) {
+        let (size, _) = layout(module, typ, span)?;
+        let mut bytes = vec![0u8; size as usize];
+        unsafe {
+            ptr::copy_nonoverlapping(address as *const u8, bytes.as_mut_ptr(), bytes.len());
+        }
+        let (element, length) = typ.elements().unwrap();
+        let lanes = (0..length as usize).map(|lane| {
+            let raw = (0..width as usize).fold(0, |raw, bit| {
+                let at = lane * width as usize + bit;
+                raw | ((bytes[at / 8] as u64 >> (at % 8)) & 1) << bit
+            });
+            Value::from_raw(raw, element)
+        });
+        return Ok(Value::Array(lanes.collect()));
+    }
+    if let Some((element, length)) = typ.elements() {
+        let (size, _) = layout(module, element, span)?;
+        let mut values = Vec::with_capacity(length as usize);
+        for index in 0..length as usize {
+            values.push(load(module, address + index * size as usize, element, span)?);
+        }
+        return Ok(Value::Array(values));
+    }
+    if let SimaType::Named(ref id) = *typ {
+        if is_record(module, id) {
+            let (offsets, _, _) = record_layout(module, id, span)?;
+            let mut values = Vec::with_capacity(offsets.len());
+            for (&(_, ref field), offset) in record_fields(module, id).iter().zip(offsets) {
+                values.push(load(module, address + offset as usize, field, span)?);
+            }
+            return Ok(Value::Record(values));
+        }
+        if let Some(cases) = variant_cases(module, id) {
+            let tag = match load(module, address, &SimaType::Basic{kind: BasicKind::Unsigned, width: 32}, span)? {
+                Value::Integer{bits, ..} => bits as usize,
+                _ => unreachable!(),
+            };
+            let payload_types = match cases.get(tag) {
+                Some(&(_, ref payload)) => payload,
+                None => return Err(Diagnostic::error(format!("Read a {} with the invalid tag {}", id, tag))
+                    .with_primary(span, "reads a variant")),
+            };
+            let (payload_offset, _, _) = variant_layout(module, id, span)?;
+            let (offsets, _, _) = struct_layout(module, payload_types, span)?;
+            let mut payload = Vec::with_capacity(offsets.len());
+            for (typ, offset) in payload_types.iter().zip(offsets) {
+                payload.push(load(module, address + (payload_offset + offset) as usize, typ, span)?);
+            }
+            return Ok(Value::Variant{tag, payload});
+        }
+    }
+    let (size, _) = layout(module, typ, span)?;
+    let mut bytes = [0u8; 8];
+    unsafe {
+        ptr::copy_nonoverlapping(address as *const u8, bytes.as_mut_ptr(), size as usize);
+    }
+    Ok(Value::from_raw(u64::from_le_bytes(bytes), typ))
+}
+
+fn store(module: &Module, address: usize, typ: &SimaType, value: Value, span: Span) -> Result<(), Diagnostic> {
+    let value = match value {
+        Value::Quotation(_) => return Err(Diagnostic::error("The interpreter cannot store quotations")
+            .with_primary(span, "stores a quotation")),
+        Value::Record(values) => {
+            let id = match *typ {
+                SimaType::Named(ref id) => id,
+                _ => return Err(Diagnostic::error(format!("Cannot store a record as {}", typ))
+                    .with_primary(span, "stores a record")),
+            };
+            let (offsets, _, _) = record_layout(module, id, span)?;
+            let fields = record_fields(module, id);
+            for ((value, &(_, ref field)), offset) in values.into_iter().zip(fields).zip(offsets) {
+                store(module, address + offset as usize, field, value, span)?;
+            }
+            return Ok(());
+        },
+        Value::Variant{tag, payload} => {
+            let id = match *typ {
+                SimaType::Named(ref id) => id,
+                _ => return Err(Diagnostic::error(format!("Cannot store a variant as {}", typ))
+                    .with_primary(span, "stores a variant")),
+            };
+            let tag_value = Value::Integer{bits: tag as u64, kind: BasicKind::Unsigned, width: 32};
+            store(module, address, &SimaType::Basic{kind: BasicKind::Unsigned, width: 32}, tag_value, span)?;
+            let payload_types = &variant_cases(module, id).unwrap()[tag].1;
+            let (payload_offset, _, _) = variant_layout(module, id, span)?;
+            let (offsets, _, _) = struct_layout(module, payload_types, span)?;
+            for ((value, typ), offset) in payload.into_iter().zip(payload_types).zip(offsets) {
+                store(module, address + (payload_offset + offset) as usize, typ, value, span)?;
+            }
+            return Ok(());
+        },
+        Value::Array(values) => {
+            if let Some(width) = packed(typ) {
+                let (size, _) = layout(module, typ, span)?;
+                let mut bytes = vec![0u8; size as usize];
+                for (lane, value) in values.iter().enumerate() {
+                    let raw = value.to_raw();
+                    for bit in 0..width as usize {
+                        let at = lane * width as usize + bit;
+                        bytes[at / 8] |= (((raw >> bit) & 1) as u8) << (at % 8);
+                    }
+                }
+                unsafe {
+                    ptr::copy_nonoverlapping(bytes.as_ptr(), address as *mut u8, bytes.len());
+                }
+                return Ok(());
+            }
+            let element = match typ.elements() {
+                Some((element, _)) => element,
+                None => return Err(Diagnostic::error(format!("Cannot store an array as {}", typ))
+                    .with_primary(span, "stores an array")),
+            };
+            let (size, _) = layout(module, element, span)?;
+            for (index, value) in values.into_iter().enumerate() {
+                store(module, address + index * size as usize, element, value, span)?;
+            }
+            return Ok(());
+        },
+        value => value,
+    };
+    let (size, _) = layout(module, typ, span)?;
+    let raw = match value {
+        Value::Integer{bits, ..} => bits,
+        _ => value.to_raw(),
+    };
+    let bytes = raw.to_le_bytes();
+    unsafe {
+        ptr::copy_nonoverlapping(bytes.as_ptr(), address as *mut u8, size as usize);
+    }
+    Ok(())
+}
+
+/// The width of the elements of a vector whose elements are not whole bytes
+/// and so are packed bit by bit in memory.
+fn packed(typ: &SimaType) -> Option<u32> {
+    match *typ {
+        SimaType::Vector{ref element, ..} if lane_width(element) % 8 != 0 => Some(lane_width(element)),
+        _ => None,
+    }
+}
+
+/// The width of the elements of a vector, which `Module::check_type` only
+/// lets numbers into.
+fn lane_width(element: &SimaType) -> u32 {
+    match *element {
+        SimaType::Basic{width, ..} => width,
+        _ => unreachable!(),
+    }
+}
+
+fn unsupported_width(width: u32, span: Span) -> Diagnostic {
+    Diagnostic::error(format!("The interpreter does not support {} bit numbers", width))
+        .with_primary(span, "only integers of up to 64 bits and f32 and f64 are supported")
+}
+
+fn mismatched_operands(word: &str, span: Span) -> Diagnostic {
+    Diagnostic::error(format!("Operands of '{}' have different types", word))
+        .with_primary(span, "expects two numbers of the same type")
+}
+
+/// Applies `operator` to two numbers, or lane by lane to two vectors.
+fn arithmetic(operator: Operator, left: Value, right: Value, span: Span) -> Result<Value, Diagnostic> {
+    use source_ast::Operator::*;
+    match (left, right) {
+        (Value::Array(l), Value::Array(r)) => {
+            if l.len() != r.len() {
+                return Err(mismatched_operands(&operator.to_string(), span));
+            }
+            let lanes = l.into_iter().zip(r).map(|(l, r)| arithmetic(operator, l, r, span));
+            Ok(Value::Array(lanes.collect::<Result<_, _>>()?))
+        },
+        (Value::Integer{bits: l, kind, width}, Value::Integer{bits: r, ..}) => {
+            if width > 64 {
+                return Err(unsupported_width(width, span));
+            }
+            if (operator == Divide || operator == Remainder) && r == 0 {
+                return Err(Diagnostic::error("Division by zero")
+                    .with_primary(span, "the divisor is 0"));
+            }
+            let bits = if kind == BasicKind::Signed {
+                let (l, r) = (signed_value(l, kind, width), signed_value(r, kind, width));
+                (match operator {
+                    Add => l.wrapping_add(r),
+                    Subtract => l.wrapping_sub(r),
+                    Multiply => l.wrapping_mul(r),
+                    Divide => l.wrapping_div(r),
+                    Remainder => l.wrapping_rem(r),
+                }) as u64
+            } else {
+                match operator {
+                    Add => l.wrapping_add(r),
+                    Subtract => l.wrapping_sub(r),
+                    Multiply => l.wrapping_mul(r),
+                    Divide => l / r,
+                    Remainder => l % r,
+                }
+            };
+            Ok(Value::Integer{bits: truncate(bits, width), kind, width})
+        },
+        (Value::Float{value: l, width}, Value::Float{value: r, ..}) => {
+            let value = match operator {
+                Add => l + r,
+                Subtract => l - r,
+                Multiply => l * r,
+                Divide => l / r,
+                Remainder => l % r,
+            };
+            let value = match width {
+                32 => value as f32 as f64,
+                64 => value,
+                _ => return Err(unsupported_width(width, span)),
+            };
+            Ok(Value::Float{value, width})
+        },
+        _ => Err(mismatched_operands(&operator.to_string(), span)),
+    }
+}
+
+fn negate(value: Value, span: Span) -> Result<Value, Diagnostic> {
+    Ok(match value {
+        Value::Integer{bits, kind, width} => Value::Integer{bits: truncate(bits.wrapping_neg(), width), kind, width},
+        Value::Float{value, width} => Value::Float{value: -value, width},
+        Value::Array(lanes) => {
+            let lanes = lanes.into_iter().map(|lane| negate(lane, span));
+            Value::Array(lanes.collect::<Result<_, _>>()?)
+        },
+        _ => return Err(Diagnostic::error("'neg' expects a number")
+            .with_primary(span, "expects a number on the stack")),
+    })
+}
+
+fn compare(comparison: Comparison, left: Value, right: Value, span: Span) -> Result<bool, Diagnostic> {
+    use source_ast::Comparison::*;
+    match (left, right) {
+        (Value::Integer{bits: l, kind, width}, Value::Integer{bits: r, ..}) => {
+            let ordering = if kind == BasicKind::Signed {
+                signed_value(l, kind, width).cmp(&signed_value(r, kind, width))
+            } else {
+                l.cmp(&r)
+            };
+            Ok(match comparison {
+                Equal => ordering == cmp::Ordering::Equal,
+                NotEqual => ordering != cmp::Ordering::Equal,
+                Less => ordering == cmp::Ordering::Less,
+                Greater => ordering == cmp::Ordering::Greater,
+                LessEqual => ordering != cmp::Ordering::Greater,
+                GreaterEqual => ordering != cmp::Ordering::Less,
+            })
+        },
+        (Value::Float{value: l, ..}, Value::Float{value: r, ..}) => Ok(match comparison {
+            Equal => l == r,
+            NotEqual => l != r,
+            Less => l < r,
+            Greater => l > r,
+            LessEqual => l <= r,
+            GreaterEqual => l >= r,
+        }),
+        _ => Err(mismatched_operands(&comparison.to_string(), span)),
+    }
+}
+
+/// The C functions the interpreter can call, checked against how they are
+/// declared. Integers and pointers are passed and returned as raw bits.
+mod libc {
+    use std::os::raw::{c_char, c_int, c_long, c_void};
+    extern "C" {
+        pub fn puts(s: *const c_char) -> c_int;
+        pub fn putchar(c: c_int) -> c_int;
+        pub fn getchar() -> c_int;
+        pub fn gets(s: *mut c_char) -> *mut c_char;
+        pub fn strlen(s: *const c_char) -> usize;
+        pub fn printf(format: *const c_char, ...) -> c_int;
+        pub fn write(fd: c_int, buf: *const c_void, count: usize) -> isize;
+        pub fn abs(n: c_int) -> c_int;
+        pub fn labs(n: c_long) -> c_long;
+        pub fn malloc(size: usize) -> *mut c_void;
+        pub fn free(p: *mut c_void);
+        pub fn exit(code: c_int) -> !;
+    }
+}
+
+/// Calls one of the supported C functions with the raw bits of `args`.
+/// Returns the raw bits of the results.
+fn call_external(id: &str, args: &[Value], span: Span) -> Result<Vec<u64>, Diagnostic> {
+    use std::os::raw::{c_int, c_long};
+
+    // everything is passed as raw bits in integer registers, which is not
+    // where C expects floats or structs, and C gets a pointer to the
+    // elements of an array, which the interpreter does not keep in memory
+    for arg in args {
+        let (kind, label) = match *arg {
+            Value::Float{..} => ("floats", "passes a float"),
+            Value::Record(_) => ("records", "passes a record"),
+            Value::Variant{..} => ("variants", "passes a variant"),
+            Value::Array(_) => ("arrays", "passes an array"),
+            _ => continue,
+        };
+        return Err(Diagnostic::error(format!("The interpreter cannot pass {} to '{}'", kind, id))
+            .with_primary(span, label));
+    }
+    let raw: Vec<u64> = args.iter().map(Value::to_raw).collect();
+    let pointer = |index: usize| raw[index] as usize;
+    unsafe {
+        Ok(match (id, raw.len()) {
+            ("puts", 1) => vec![libc::puts(pointer(0) as *const _) as u64],
+            ("putchar", 1) => vec![libc::putchar(raw[0] as c_int) as u64],
+            ("getchar", 0) => vec![libc::getchar() as u64],
+            ("gets", 1) => vec![libc::gets(pointer(0) as *mut _) as u64],
+            ("strlen", 1) => vec![libc::strlen(pointer(0) as *const _) as u64],
+            ("printf", count) if 1 <= count && count <= 7 => {
+                let format = pointer(0) as *const _;
+                let printed = match count {
+                    1 => libc::printf(format),
+                    2 => libc::printf(format, raw[1]),
+                    3 => libc::printf(format, raw[1], raw[2]),
+                    4 => libc::printf(format, raw[1], raw[2], raw[3]),
+                    5 => libc::printf(format, raw[1], raw[2], raw[3], raw[4]),
+                    6 => libc::printf(format, raw[1], raw[2], raw[3], raw[4], raw[5]),
+                    _ => libc::printf(format, raw[1], raw[2], raw[3], raw[4], raw[5], raw[6]),
+                };
+                vec![printed as u64]
+            },
+            ("write", 3) => vec![libc::write(raw[0] as c_int, pointer(1) as *const _, raw[2] as usize) as u64],
+            ("abs", 1) => vec![libc::abs(raw[0] as c_int) as u64],
+            ("labs", 1) => vec![libc::labs(raw[0] as c_long) as u64],
+            ("malloc", 1) => vec![libc::malloc(raw[0] as usize) as u64],
+            ("free", 1) => {
+                libc::free(pointer(0) as *mut _);
+                vec![]
+            },
+            ("exit", 1) => libc::exit(raw[0] as c_int),
+            _ => return Err(Diagnostic::error(format!("The interpreter cannot call the external word '{}'", id))
+                .with_primary(span, "only puts, putchar, getchar, gets, strlen, printf with up to 6 extra arguments, write, abs, labs, malloc, free and exit are supported")),
+        })
+    }
+}
@@ -1,25 +1,213 @@
 extern crate lalrpop_util;
+#[cfg(feature = "llvm")]
 extern crate inkwell;
+mod diagnostic;
 mod source_ast;
 mod source_grammar;
+#[cfg(feature = "llvm")]
 mod codegen;
+mod typecheck;
+#[cfg(feature = "llvm")]
+mod repl;
+mod interpreter;
 
 use std::env;
-use std::path::{Path};
-use source_ast::parse_file;
-use codegen::print_llvm_ir;
+use std::fs::File;
+use std::io::Read;
+use std::process;
+use diagnostic::Diagnostic;
+use source_ast::parse_module;
+#[cfg(feature = "llvm")]
+use std::fs;
+#[cfg(feature = "llvm")]
+use std::process::Command;
+#[cfg(feature = "llvm")]
+use std::path::{Path, PathBuf};
+#[cfg(feature = "llvm")]
+use codegen::Emit;
+
+const USAGE: &str = "usage: sima [--emit=llvm-ir|bitcode|asm|obj|exe] [-o <path>] <file>
+       sima run <file> [args...]
+       sima interp <file>
+       sima repl";
+
+/// What the command line asks for. Executables are linked from an object
+/// file, so they are not one of the `Emit` kinds of the code generator.
+#[cfg(feature = "llvm")]
+struct Options {
+    input: String,
+    emit: Option<Emit>,
+    output: Option<PathBuf>,
+}
+
+#[cfg(feature = "llvm")]
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut input = None;
+    let mut emit = Some(Emit::LlvmIr);
+    let mut output = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg.starts_with("--emit=") {
+            emit = match &arg["--emit=".len()..] {
+                "llvm-ir" => Some(Emit::LlvmIr),
+                "bitcode" => Some(Emit::Bitcode),
+                "asm" => Some(Emit::Assembly),
+                "obj" => Some(Emit::Object),
+                "exe" => None,
+                other => return Err(format!("unknown output kind '{}'", other)),
+            };
+        } else if arg == "-o" {
+            match args.next() {
+                Some(path) => output = Some(PathBuf::from(path)),
+                None => return Err("-o needs a path".to_string()),
+            }
+        } else if arg.starts_with('-') {
+            return Err(format!("unknown option '{}'", arg));
+        } else if input.is_some() {
+            return Err(format!("more than one input file given: '{}'", arg));
+        } else {
+            input = Some(arg.clone());
+        }
+    }
+    match input {
+        Some(input) => Ok(Options { input, emit, output }),
+        None => Err("no input file specified".to_string()),
+    }
+}
 
 fn main() {
-    let args : Vec<String> = env::args().collect();
-    if args.len() == 1{
-        println!("no input file specified");
-        return;
-    }
-    let filename =  Path::new(&args[1]);
-    let mut outname = filename.to_path_buf();
-    outname.set_extension("ll");
-    let mut outname = outname.to_str().unwrap().to_string();
-    outname.shrink_to_fit();
-    let module = parse_file(filename).unwrap();
-    print_llvm_ir(module, &outname);
+    let args : Vec<String> = env::args().skip(1).collect();
+    match args.first().map(|a| a.as_str()) {
+        Some("interp") => interpret(&args[1..]),
+        _ => compile(&args),
+    }
+}
+
+/// Everything but `sima interp` needs LLVM. Building without the default
+/// `llvm` feature leaves it out, for machines that do not have it.
+#[cfg(not(feature = "llvm"))]
+fn compile(_args: &[String]) {
+    usage_error("sima was built without the 'llvm' feature, only 'sima interp' is available");
+}
+
+#[cfg(feature = "llvm")]
+fn compile(args: &[String]) {
+    match args.first().map(|a| a.as_str()) {
+        Some("run") => return run(&args[1..]),
+        Some("repl") => return repl::run(),
+        _ => {},
+    }
+    let options = match parse_args(args) {
+        Ok(options) => options,
+        Err(message) => usage_error(&message),
+    };
+    let filename = Path::new(&options.input);
+    let outname = options.output.clone().unwrap_or_else(|| {
+        let extension = options.emit.map_or("", |emit| emit.extension());
+        filename.with_extension(extension)
+    });
+
+    let (source, module) = load(&options.input);
+    match options.emit {
+        Some(emit) => codegen::emit(module, emit, &outname).unwrap_or_else(|e| source.fail(vec![e])),
+        None => {
+            // the object file goes into a directory of its own, so that it
+            // cannot overwrite a file of the user's, or the output itself
+            let dir = env::temp_dir().join(format!("sima-{}", process::id()));
+            let object = dir.join("main.o");
+            let linked = fs::create_dir_all(&dir)
+                .map_err(|e| Diagnostic::error(format!("Could not create '{}': {}", dir.display(), e)))
+                .and_then(|_| codegen::emit(module, Emit::Object, &object))
+                .and_then(|_| link(&object, &outname));
+            let _ = fs::remove_dir_all(&dir);
+            linked.unwrap_or_else(|e| source.fail(vec![e]));
+        }
+    }
+}
+
+/// `sima run <file> [args...]`: compiles the file in memory, runs its `main`
+/// and exits with what it returned.
+#[cfg(feature = "llvm")]
+fn run(args: &[String]) {
+    let input = match args.first() {
+        Some(input) => input,
+        None => usage_error("no input file specified"),
+    };
+    let (source, module) = load(input);
+    match codegen::run(module, args) {
+        Ok(code) => process::exit(code),
+        Err(e) => source.fail(vec![e]),
+    }
+}
+
+/// `sima interp <file>`: like `run`, but evaluates the AST directly instead
+/// of compiling it.
+fn interpret(args: &[String]) {
+    let input = match args.first() {
+        Some(input) => input,
+        None => usage_error("no input file specified"),
+    };
+    let (source, module) = load(input);
+    match interpreter::run(&module) {
+        Ok(code) => process::exit(code),
+        Err(e) => source.fail(vec![e]),
+    }
+}
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("error: {}", message);
+    eprintln!("{}", USAGE);
+    process::exit(2);
+}
+
+/// A source file that diagnostics are reported against.
+struct Source {
+    name: String,
+    contents: String,
+}
+impl Source {
+    /// Prints the diagnostics and exits.
+    fn fail(&self, errors: Vec<Diagnostic>) -> ! {
+        for error in errors {
+            eprint!("{}", error.render(&self.name, &self.contents));
+        }
+        process::exit(1);
+    }
+}
+
+/// Reads, parses and checks a source file, exiting with the diagnostics if
+/// that fails.
+fn load(input: &str) -> (Source, source_ast::Module) {
+    let mut contents = String::new();
+    if let Err(e) = File::open(input).and_then(|mut f| f.read_to_string(&mut contents)) {
+        eprintln!("error: could not read '{}': {}", input, e);
+        process::exit(1);
+    }
+    let source = Source { name: input.to_string(), contents };
+
+    let (module, errors) = parse_module(&source.contents);
+    if !errors.is_empty() {
+        source.fail(errors);
+    }
+    module.calc_arieties().unwrap_or_else(|e| source.fail(e));
+    typecheck::check_module(&module).unwrap_or_else(|e| source.fail(e));
+    (source, module)
+}
+
+/// Links an object file into an executable with the system C compiler, which
+/// also pulls in the C runtime and libc. `CC` overrides the compiler.
+#[cfg(feature = "llvm")]
+fn link(object: &Path, executable: &Path) -> Result<(), Diagnostic> {
+    let compiler = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let status = Command::new(&compiler)
+        .arg(object)
+        .arg("-o")
+        .arg(executable)
+        .status()
+        .map_err(|e| Diagnostic::error(format!("Could not run the linker '{}': {}", compiler, e)))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(Diagnostic::error(format!("Linking '{}' failed: {} exited with {}", executable.display(), compiler, status)))
+    }
 }
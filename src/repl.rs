@@ -0,0 +1,114 @@
+use std::io::{self, BufRead, Write};
+use codegen::{self, Constant};
+use diagnostic::Diagnostic;
+use source_ast::{self, Expression, Item, Module, SimaType, BasicKind};
+use typecheck;
+
+/// `sima repl`: reads declarations and definitions, which end with `;`, and
+/// expressions, which are run right away on a data stack that is kept from
+/// line to line. The stack is printed after every line.
+///
+/// Only numbers can stay on the stack. Anything else, such as the pointer a
+/// string literal leaves, would point into the code compiled for its line,
+/// which is gone once the line has run, so a line that leaves one behind is
+/// rejected as a whole.
+pub fn run() {
+    let stdin = io::stdin();
+    let mut repl = Repl::new();
+    loop {
+        print!("> ");
+        io::stdout().flush().ok();
+        let mut line = String::new();
+        match stdin.lock().read_line(&mut line) {
+            Ok(0) => break,
+            Ok(_) => {},
+            Err(e) => {
+                eprintln!("error: could not read the input: {}", e);
+                break;
+            },
+        }
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        match repl.enter(line) {
+            Ok(()) => println!("{}", repl.show_stack()),
+            Err(errors) => for error in errors {
+                eprint!("{}", error.render("<repl>", &repl.transcript));
+            },
+        }
+    }
+}
+
+struct Repl {
+    module: Module,
+    stack: Vec<(Constant, SimaType)>,
+    /// Every line entered so far. Spans are offsets into it, so that
+    /// diagnostics can also show the lines that earlier items come from.
+    transcript: String,
+}
+
+impl Repl {
+    fn new() -> Repl {
+        Repl {
+            module: Module::new("repl"),
+            stack: Vec::new(),
+            transcript: String::new(),
+        }
+    }
+
+    fn enter(&mut self, line: &str) -> Result<(), Vec<Diagnostic>> {
+        // the parser sees the line at the offset it has in the transcript,
+        // with the lines before it blanked out
+        let source = format!("{}{}", " ".repeat(self.transcript.len()), line);
+        self.transcript.push_str(line);
+        self.transcript.push('\n');
+        if line.ends_with(';') {
+            self.add_item(source_ast::parse_item(&source)?)
+        } else {
+            self.evaluate(source_ast::parse_expression(&source)?)
+        }
+    }
+
+    /// Adds a declaration or definition, unless the module would no longer
+    /// check with it.
+    fn add_item(&mut self, item: Item) -> Result<(), Vec<Diagnostic>> {
+        let mut module = self.module.clone();
+        module.add_item(item).map_err(|e| vec![e])?;
+        module.calc_arieties()?;
+        typecheck::check_module(&module)?;
+        self.module = module;
+        Ok(())
+    }
+
+    /// Runs an expression on the stack. The stack stays as it was if that
+    /// fails.
+    fn evaluate(&mut self, expr: Expression) -> Result<(), Vec<Diagnostic>> {
+        let mut effects = vec![None; self.stack.len()];
+        expr.calc_ariety(&self.module, &mut effects).map_err(|e| vec![e])?;
+        let types: Vec<SimaType> = self.stack.iter().map(|&(_, ref typ)| typ.clone()).collect();
+        let result = typecheck::check_expression(&self.module, &types, &expr)?;
+        let values = codegen::evaluate(self.module.clone(), &self.stack, &expr, &result)
+            .map_err(|e| vec![e])?;
+        self.stack = values.into_iter().zip(result).collect();
+        Ok(())
+    }
+
+    /// The stack, bottom first, followed by the types of its values.
+    fn show_stack(&self) -> String {
+        let values: Vec<String> = self.stack.iter().map(|&(value, ref typ)| show_value(value, typ)).collect();
+        let types: Vec<String> = self.stack.iter().map(|&(_, ref typ)| typ.to_string()).collect();
+        format!("[{}] : {}", values.join(" "), types.join(" "))
+    }
+}
+
+fn show_value(value: Constant, typ: &SimaType) -> String {
+    match (value, typ) {
+        (Constant::Float(f), _) => f.to_string(),
+        (Constant::Integer(bits), &SimaType::Basic{kind: BasicKind::Signed, width}) if width > 1 => {
+            let shift = 64 - width;
+            (((bits << shift) as i64) >> shift).to_string()
+        },
+        (Constant::Integer(bits), _) => bits.to_string(),
+    }
+}
@@ -1,58 +1,343 @@
-use std::error::Error;
 use std::cell::Cell;
 use std::collections::HashMap;
-use std::path::Path;
+use std::fmt;
+use std::str;
+use lalrpop_util::ParseError;
+use diagnostic::{Diagnostic, Span};
 
-pub fn parse_file(filename: &Path) -> Result<Module, Box<Error>> {
-    use std::fs::File;
-    use std::io::Read;
+/// Parses a source file. Malformed items are skipped, so the module holds
+/// everything that could be parsed, even if there are errors.
+pub fn parse_module(source: &str) -> (Module, Vec<Diagnostic>) {
     use source_grammar::parse_Module;
 
-    let mut file = File::open(filename)?;
-    let mut contents = String::new();
-    file.read_to_string(&mut contents)?;
-    Ok(parse_Module(&contents).unwrap())
+    let mut recovered = Vec::new();
+    let mut errors = Vec::new();
+    let parsed = parse_Module(&mut recovered, &mut errors, source);
+    errors.extend(recovered.into_iter().map(|r| parse_error(source, r.error)));
+    let module = match parsed {
+        Ok((id, items)) => {
+            let mut module = Module::new(&id);
+            errors.extend(items.into_iter().filter_map(|item| module.add_item(item).err()));
+            module
+        }
+        Err(e) => {
+            errors.push(parse_error(source, e));
+            Module::new("")
+        }
+    };
+    errors.sort_by_key(|e| e.primary.as_ref().map(|label| label.span.start));
+    (module, errors)
+}
+
+/// Parses a single declaration or definition, as typed into the REPL.
+pub fn parse_item(source: &str) -> Result<Item, Vec<Diagnostic>> {
+    use source_grammar::parse_ReplItem;
+
+    let mut recovered = Vec::new();
+    let mut errors = Vec::new();
+    let parsed = parse_ReplItem(&mut recovered, &mut errors, source);
+    errors.extend(recovered.into_iter().map(|r| parse_error(source, r.error)));
+    match parsed {
+        Ok(Some(item)) if errors.is_empty() => Ok(item),
+        Ok(_) => Err(errors),
+        Err(e) => {
+            errors.push(parse_error(source, e));
+            Err(errors)
+        }
+    }
+}
+
+/// Parses a bare expression, as typed into the REPL.
+pub fn parse_expression(source: &str) -> Result<Expression, Vec<Diagnostic>> {
+    use source_grammar::parse_ReplExpression;
+
+    let mut errors = Vec::new();
+    match parse_ReplExpression(&mut Vec::new(), &mut errors, source) {
+        Ok(expr) if errors.is_empty() => Ok(expr),
+        Ok(_) => Err(errors),
+        Err(e) => {
+            errors.push(parse_error(source, e));
+            Err(errors)
+        }
+    }
+}
+
+/// Reports a token the grammar matched but could not make sense of, such as
+/// a string literal with an invalid escape. Parsing goes on with `fallback`,
+/// which is never checked or run because the file has errors.
+pub fn recover<T>(errors: &mut Vec<Diagnostic>, parsed: Result<T, &'static str>, span: Span, label: &str, fallback: T) -> T {
+    parsed.unwrap_or_else(|error| {
+        errors.push(Diagnostic::error(error).with_primary(span, label));
+        fallback
+    })
+}
+
+fn parse_error<T, E: fmt::Display>(source: &str, error: ParseError<usize, T, E>) -> Diagnostic {
+    match error {
+        ParseError::InvalidToken{location} => Diagnostic::error("invalid token")
+            .with_primary(Span::new(location, location + 1), "no token starts here"),
+        ParseError::UnrecognizedToken{token: Some((start, _, end)), expected} =>
+            Diagnostic::error(format!("unexpected '{}'", &source[start..end]))
+                .with_primary(Span::new(start, end), expected_tokens(&expected)),
+        ParseError::UnrecognizedToken{token: None, expected} => Diagnostic::error("unexpected end of file")
+            .with_primary(Span::new(source.len(), source.len()), expected_tokens(&expected)),
+        ParseError::ExtraToken{token: (start, _, end)} =>
+            Diagnostic::error(format!("unexpected '{}'", &source[start..end]))
+                .with_primary(Span::new(start, end), "expected the end of the file"),
+        ParseError::User{error} => Diagnostic::error(error.to_string()),
+    }
+}
+
+fn expected_tokens(expected: &[String]) -> String {
+    match expected.len() {
+        0 => String::new(),
+        1 => format!("expected {}", expected[0]),
+        _ => format!("expected one of {}", expected.join(", ")),
+    }
+}
+
+/// A top level item of a source file.
+#[derive(Debug, Clone)]
+pub enum Item{
+    /// `variadic` is set for C functions such as `printf :: i8* ... -> i32;`.
+    Declaration{id: String, typ: SimaType, variadic: bool, span: Span},
+    Definition{id: String, expr: Expression, span: Span},
+    Type{id: String, definition: TypeDefinition, span: Span},
+}
+
+/// What a type declared at module level stands for.
+#[derive(Debug, Clone)]
+pub enum TypeDefinition{
+    /// `opaque FILE;`: a type only C knows the layout of. Pointers to
+    /// different opaque types cannot be mixed up.
+    Opaque,
+    /// `record Point { x: i32, y: i32 };`: a struct laid out like in C.
+    Record{fields: Vec<(String, SimaType)>},
+    /// `variant Result { Ok(i8*), Err(i32) };`: a tag telling which case a
+    /// value is, followed by room for the payload of the largest case.
+    Variant{cases: Vec<(String, Vec<SimaType>)>},
+}
+
+impl TypeDefinition{
+    /// The index and type of a field of a record.
+    pub fn field(&self, name: &str) -> Option<(usize, &SimaType)>{
+        match *self{
+            TypeDefinition::Record{ref fields} => fields.iter()
+                .position(|&(ref id, _)| id == name)
+                .map(|index| (index, &fields[index].1)),
+            _ => None,
+        }
+    }
+    /// The tag and payload types of a case of a variant.
+    pub fn case(&self, name: &str) -> Option<(usize, &[SimaType])>{
+        match *self{
+            TypeDefinition::Variant{ref cases} => cases.iter()
+                .position(|&(ref id, _)| id == name)
+                .map(|tag| (tag, &cases[tag].1[..])),
+            _ => None,
+        }
+    }
+}
+
+/// A type declared at module level, which `SimaType::Named` refers to.
+#[derive(Debug, Clone)]
+pub struct NamedType{
+    pub definition: TypeDefinition,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
 pub struct Module{
     pub id: String,
     pub functions: HashMap<String, Function>,
+    /// The names of `functions` in the order they were declared. Passes go
+    /// through the functions in this order, so that the output does not
+    /// depend on how the map is laid out.
+    pub order: Vec<String>,
+    pub types: HashMap<String, NamedType>,
 }
 impl Module{
     pub fn new(id: &str) -> Module{
         Module{
             id: id.to_string(),
             functions: HashMap::new(),
+            order: Vec::new(),
+            types: HashMap::new(),
+        }
+    }
+    pub fn add_item(&mut self, item: Item) -> Result<(), Diagnostic>{
+        match item{
+            Item::Declaration{id, typ, variadic, span} => self.add_declaration(&id, typ, variadic, span),
+            Item::Definition{id, expr, span} => self.add_definition(&id, expr, span),
+            Item::Type{id, definition, span} => self.add_type(&id, definition, span),
+        }
+    }
+    pub fn add_type(&mut self, id: &str, definition: TypeDefinition, span: Span) -> Result<(), Diagnostic>{
+        if let Some(previous) = self.types.get(id){
+            return Err(Diagnostic::error(format!("Type '{}' has multiple declarations", id))
+                .with_primary(span, "declared again here")
+                .with_secondary(previous.span, "first declared here"));
+        }
+        match definition{
+            TypeDefinition::Record{ref fields} => {
+                // the constructor of a record is a word named like it
+                if let Some(f) = self.functions.get(id){
+                    return Err(Diagnostic::error(format!("Record '{}' has the name of a function", id))
+                        .with_primary(span, "declared here")
+                        .with_secondary(f.span, "the function is declared here"));
+                }
+                for (index, &(ref field, ref typ)) in fields.iter().enumerate(){
+                    if fields[..index].iter().any(|&(ref other, _)| other == field){
+                        return Err(Diagnostic::error(format!("Record '{}' has several fields named '{}'", id, field))
+                            .with_primary(span, "declared here"));
+                    }
+                    if typ.holds(id){
+                        return Err(Diagnostic::error(format!("Record '{}' contains itself", id))
+                            .with_primary(span, format!("field '{}' would have to be behind a pointer", field)));
+                    }
+                }
+            },
+            TypeDefinition::Variant{ref cases} => {
+                for (index, &(ref case, ref payload)) in cases.iter().enumerate(){
+                    if cases[..index].iter().any(|&(ref other, _)| other == case){
+                        return Err(Diagnostic::error(format!("Variant '{}' has several cases named '{}'", id, case))
+                            .with_primary(span, "declared here"));
+                    }
+                    if payload.iter().any(|typ| typ.holds(id)){
+                        return Err(Diagnostic::error(format!("Variant '{}' contains itself", id))
+                            .with_primary(span, format!("case '{}' would have to hold it behind a pointer", case)));
+                    }
+                }
+            },
+            TypeDefinition::Opaque => {},
+        }
+        // records and variants may point to themselves, so the type is known
+        // while the types it is made of are checked
+        self.types.insert(id.to_string(), NamedType{definition, span});
+        let checked = match self.types[id].definition{
+            TypeDefinition::Record{ref fields} => fields.iter().map(|&(_, ref t)| self.check_type(t, span)).collect(),
+            TypeDefinition::Variant{ref cases} => cases.iter()
+                .flat_map(|&(_, ref payload)| payload.iter())
+                .map(|t| self.check_type(t, span))
+                .collect(),
+            TypeDefinition::Opaque => Ok(()),
+        };
+        if checked.is_err(){
+            self.types.remove(id);
+        }
+        checked
+    }
+    /// Checks that the named types `typ` is built from are declared. Like
+    /// functions, types have to be declared before they are used. Opaque
+    /// types have no known layout, so they can only appear behind a pointer.
+    pub fn check_type(&self, typ: &SimaType, span: Span) -> Result<(), Diagnostic>{
+        match *typ{
+            SimaType::Function{ref args, ref ret} => {
+                for t in args.iter().chain(ret.iter()){
+                    self.check_type(t, span)?;
+                }
+                Ok(())
+            },
+            SimaType::Pointer{ref target} if self.is_opaque(target) => Ok(()),
+            SimaType::Pointer{ref target} | SimaType::Array{element: ref target, ..} => self.check_type(target, span),
+            SimaType::Vector{ref element, length} => match **element{
+                SimaType::Basic{..} if length > 0 => self.check_type(element, span),
+                _ => Err(Diagnostic::error(format!("{} is not a valid vector type", typ))
+                    .with_primary(span, "vectors hold one or more numbers")),
+            },
+            SimaType::Basic{kind: BasicKind::Float, width} if ![16, 32, 64, 128].contains(&width) =>
+                Err(Diagnostic::error(format!("Unsupported floating point type 'f{}'", width))
+                    .with_primary(span, "only f16, f32, f64 and f128 exist")),
+            SimaType::Named(ref id) if !self.types.contains_key(id) =>
+                Err(Diagnostic::error(format!("Type '{}' is not declared in Module '{}'", id, self.id))
+                    .with_primary(span, "used here")),
+            _ if self.is_opaque(typ) =>
+                Err(Diagnostic::error(format!("Opaque type '{}' can only be used behind a pointer", typ))
+                    .with_primary(span, "its layout is only known to C")),
+            _ => Ok(()),
         }
     }
-    pub fn add_definition(&mut self, id: &str, expr: Expression){
-        let f = self.functions.get_mut(id);
-        assert!(f.is_some(), "Function '{}' must be declared before it gets defined", id);
-        let f = f.unwrap();
-        assert!(f.expr.is_none(), "Function '{}' has multiple definitions", id);
+    fn is_opaque(&self, typ: &SimaType) -> bool{
+        match *typ{
+            SimaType::Opaque => true,
+            SimaType::Named(ref id) => match self.types.get(id){
+                Some(&NamedType{definition: TypeDefinition::Opaque, ..}) => true,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+    pub fn add_definition(&mut self, id: &str, expr: Expression, span: Span) -> Result<(), Diagnostic>{
+        let f = match self.functions.get_mut(id){
+            Some(f) => f,
+            None => return Err(Diagnostic::error(format!("Function '{}' must be declared before it gets defined", id))
+                .with_primary(span, "defined here without a declaration")),
+        };
+        if let Some(ref previous) = f.expr{
+            return Err(Diagnostic::error(format!("Function '{}' has multiple definitions", id))
+                .with_primary(span, "defined again here")
+                .with_secondary(previous.span, "first defined here"));
+        }
+        if f.variadic{
+            return Err(Diagnostic::error(format!("Function '{}' is variadic, so it can only be declared", id))
+                .with_primary(span, "defined here")
+                .with_secondary(f.span, "declared with '...' here"));
+        }
         f.expr = Some(expr);
+        Ok(())
     }
-    pub fn add_declaration(&mut self, id: &str, typ: SimaType){
-        assert!(self.functions.get(id).is_none(), "Function '{}' has multiple declarations", id);
+    pub fn add_declaration(&mut self, id: &str, typ: SimaType, variadic: bool, span: Span) -> Result<(), Diagnostic>{
+        self.check_type(&typ, span)?;
+        if let Some(&NamedType{definition: TypeDefinition::Record{..}, span: record}) = self.types.get(id){
+            return Err(Diagnostic::error(format!("Function '{}' has the name of a record", id))
+                .with_primary(span, "declared here")
+                .with_secondary(record, "the record is declared here"));
+        }
+        if let Some(previous) = self.functions.get(id){
+            return Err(Diagnostic::error(format!("Function '{}' has multiple declarations", id))
+                .with_primary(span, "declared again here")
+                .with_secondary(previous.span, "first declared here"));
+        }
+        self.order.push(id.to_string());
         self.functions.insert(id.to_string(), Function{
             typ,
+            variadic,
             expr: None,
+            span,
         });
+        Ok(())
+    }
+    /// The functions in the order they were declared.
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = (&'a String, &'a Function)> + 'a{
+        self.order.iter().map(move |id| (id, &self.functions[id]))
     }
-    pub fn calc_arieties(&self){
-        for (_, fun) in self.functions.iter(){
+    pub fn calc_arieties(&self) -> Result<(), Vec<Diagnostic>>{
+        let mut errors = Vec::new();
+        for (_, fun) in self.iter(){
             if let Some(ref expr) = fun.expr{
-                expr.calc_ariety(&self);
+                let mut stack = fun.arg_effects();
+                if let Err(e) = expr.calc_ariety(&self, &mut stack){
+                    errors.push(e);
+                }
             }
-        }        
+        }
+        if errors.is_empty(){
+            Ok(())
+        }else{
+            Err(errors)
+        }
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct Function{
     pub typ: SimaType,
+    /// Whether the function takes more arguments than its type lists. Only
+    /// external functions can be variadic.
+    pub variadic: bool,
     pub expr: Option<Expression>,
+    /// Where the function is declared.
+    pub span: Span,
 }
 impl Function{
     pub fn in_ariety(&self) -> usize{
@@ -67,83 +352,752 @@ impl Function{
             _ => 1,
         }
     }
+    pub fn arg_effects(&self) -> Vec<Option<StackEffect>>{
+        match self.typ{
+            SimaType::Function{ref args, ..} => args.iter().map(SimaType::effect).collect(),
+            _ => vec![None],
+        }
+    }
+    pub fn ret_effects(&self) -> Vec<Option<StackEffect>>{
+        match self.typ{
+            SimaType::Function{ref ret, ..} => ret.iter().map(SimaType::effect).collect(),
+            _ => vec![None],
+        }
+    }
 }
 
-#[derive(Debug, Clone)]
+/// How many values a piece of code takes from the stack and leaves on it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct StackEffect{
+    pub in_ariety: usize,
+    pub out_ariety: usize,
+}
+impl fmt::Display for StackEffect{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+        write!(f, "{} -> {}", self.in_ariety, self.out_ariety)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum SimaType{
     Function{args: Vec<SimaType>, ret: Vec<SimaType>},
     Pointer{target: Box<SimaType>},
-    Basic{id: String},
+    Basic{kind: BasicKind, width: u32},
     Opaque,
+    /// A type declared at module level.
+    Named(String),
+    /// `[i32; 16]`: a fixed number of values, one after the other.
+    Array{element: Box<SimaType>, length: u32},
+    /// `<4 x f32>`: numbers that arithmetic works on element by element.
+    /// The builder of the LLVM 3.7 bindings only does arithmetic on scalars,
+    /// so the code generator computes one lane at a time, and it is up to
+    /// LLVM's vectorizers whether that ends up in SIMD instructions.
+    Vector{element: Box<SimaType>, length: u32},
+}
+impl SimaType{
+    /// The stack effect of a quotation of this type, if it is one.
+    pub fn effect(&self) -> Option<StackEffect>{
+        match *self{
+            SimaType::Function{ref args, ref ret} => Some(StackEffect{
+                in_ariety: args.len(),
+                out_ariety: ret.len(),
+            }),
+            _ => None,
+        }
+    }
+    /// Parses a `SIMPLETYPE` token such as `i32`, `u8` or `f64`.
+    pub fn basic(id: &str) -> Result<SimaType, &'static str>{
+        let kind = match &id[..1]{
+            "i" => BasicKind::Signed,
+            "u" => BasicKind::Unsigned,
+            _ => BasicKind::Float,
+        };
+        let width = id[1..].parse().map_err(|_| "invalid width")?;
+        Ok(SimaType::Basic{kind, width})
+    }
+    /// Whether values of this type use signed integer instructions. This is
+    /// decided by the `i` or `u` prefix of the type.
+    pub fn is_signed(&self) -> bool{
+        match *self{
+            SimaType::Basic{kind: BasicKind::Signed, ..} => true,
+            _ => false,
+        }
+    }
+    pub fn is_float(&self) -> bool{
+        match *self{
+            SimaType::Basic{kind: BasicKind::Float, ..} => true,
+            _ => false,
+        }
+    }
+    /// Whether C takes and returns values of this type the way LLVM passes
+    /// them. Each C ABI passes records and variants its own way, and only
+    /// vectors that fit a single SSE register are passed the same way.
+    pub fn is_c_compatible(&self) -> bool{
+        match *self{
+            SimaType::Named(_) => false,
+            SimaType::Vector{ref element, length} => match **element{
+                SimaType::Basic{width, ..} => width % 8 == 0 && length.is_power_of_two() && width as u64 * length as u64 == 128,
+                _ => false,
+            },
+            _ => true,
+        }
+    }
+    /// The element type and length of an array or vector type.
+    pub fn elements(&self) -> Option<(&SimaType, u32)>{
+        match *self{
+            SimaType::Array{ref element, length} | SimaType::Vector{ref element, length} => Some((element, length)),
+            _ => None,
+        }
+    }
+    /// Whether a value of this type holds a value of the named type `id`
+    /// itself, rather than behind a pointer.
+    fn holds(&self, id: &str) -> bool{
+        match *self{
+            SimaType::Named(ref named) => named == id,
+            SimaType::Array{ref element, ..} => element.holds(id),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for SimaType{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+        match *self{
+            SimaType::Function{ref args, ref ret} => {
+                f.write_str("[")?;
+                for arg in args{
+                    write!(f, "{} ", arg)?;
+                }
+                f.write_str("->")?;
+                for r in ret{
+                    write!(f, " {}", r)?;
+                }
+                f.write_str("]")
+            },
+            SimaType::Pointer{ref target} => write!(f, "{}*", target),
+            SimaType::Basic{kind, width} => write!(f, "{}{}", kind, width),
+            SimaType::Opaque => f.write_str("opaque"),
+            SimaType::Named(ref id) => f.write_str(id),
+            SimaType::Array{ref element, length} => write!(f, "[{}; {}]", element, length),
+            SimaType::Vector{ref element, length} => write!(f, "<{} x {}>", length, element),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BasicKind{
+    Signed,
+    Unsigned,
+    Float,
+}
+impl fmt::Display for BasicKind{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+        f.write_str(match *self{
+            BasicKind::Signed => "i",
+            BasicKind::Unsigned => "u",
+            BasicKind::Float => "f",
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Expression{
+    pub kind: ExpressionKind,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
-pub enum Expression{
+pub enum ExpressionKind{
     Concat{left: Box<Expression>, right: Box<Expression>},
     Sidecat{left: Box<Expression>, right: Box<Expression>},
     Block{inner: Box<Expression>},
-    StringLiteral(String),
-    Number(String),
-    Identifier{id: String, in_ariety: Cell<usize>, out_ariety: Cell<usize>},
+    /// A string literal as written and the bytes it stands for, without the
+    /// terminating NUL.
+    StringLiteral{literal: String, bytes: Vec<u8>},
+    /// A `b"..."` literal, which pushes a pointer to its bytes and their
+    /// count instead of relying on a terminating NUL.
+    ByteString{literal: String, bytes: Vec<u8>},
+    /// An integer literal as written. `value` is `None` if it does not fit
+    /// into an `i128`. The type of a literal without a suffix is filled into
+    /// `typ` by the type checker.
+    Number{literal: String, value: Option<i128>, suffix: Option<(BasicKind, u32)>, typ: Cell<Option<(BasicKind, u32)>>},
+    /// A float literal as written. Its width is filled into `width` by the
+    /// type checker.
+    Float{literal: String, width: Cell<Option<u32>>},
+    /// A word. `extra` is the `N` of `printf/N`, the number of values passed
+    /// to a variadic function on top of its declared arguments.
+    Identifier{id: String, extra: Option<usize>, in_ariety: Cell<usize>, out_ariety: Cell<usize>},
+    Apply{in_ariety: Cell<usize>, out_ariety: Cell<usize>},
+    Conditional{in_ariety: Cell<usize>, out_ariety: Cell<usize>},
+    While{condition: Cell<StackEffect>, body: Cell<StackEffect>},
+    Times{body: Cell<StackEffect>},
+    Arithmetic(Operator),
+    Negate,
+    Comparison(Comparison),
     Duplicate,
     Discard,
     Exchange,
     Keep,
+    /// `@`: reads the value a pointer points to.
+    Load,
+    /// `!`: writes a value through a pointer, `value pointer !`.
+    Store,
+    /// `+ptr`: moves a pointer by a number of elements.
+    Offset,
+    /// `index`: reads the element at an offset from a pointer, like `+ptr @`.
+    Index,
+    /// `sizeof<T>`: the size of a type in bytes, from the target's data layout.
+    SizeOf(SimaType),
+    /// `alignof<T>`: the alignment of a type in bytes.
+    AlignOf(SimaType),
+    /// `as<T>`, or `>T` for a basic type: converts a number or pointer.
+    Cast(SimaType),
+    /// `Point.x`, `Point.x!` or `Point->x`.
+    Field{record: String, field: String, access: FieldAccess},
+    /// `Result::Ok`: makes a case of a variant out of its payload, which is
+    /// `payload` values.
+    Case{variant: String, case: String, payload: Cell<usize>},
+    /// `match Result { Ok [...], Err [...] }`: takes a variant and runs the
+    /// block of its case on the values below it and the case's payload.
+    Match{variant: String, cases: Vec<MatchCase>, in_ariety: Cell<usize>, out_ariety: Cell<usize>},
+    /// `pack<T>`: makes an array or vector out of as many values as it has
+    /// elements.
+    Pack(SimaType),
+    /// `extract`: the element of an array or vector at an index,
+    /// `aggregate index extract`.
+    Extract,
+    /// `insert`: replaces an element of an array or vector,
+    /// `aggregate value index insert`.
+    Insert,
+}
+
+/// One block of a `match`.
+#[derive(Debug, Clone)]
+pub struct MatchCase{
+    pub case: String,
+    pub body: Expression,
+    pub span: Span,
+}
+
+/// What a field word does with the field of a record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldAccess{
+    /// `Point.x`: takes a record and leaves the field.
+    Get,
+    /// `Point.x!`: takes a record and a value and leaves the record with
+    /// the field replaced.
+    Set,
+    /// `Point->x`: takes a pointer to a record and leaves a pointer to the
+    /// field, for use with `@` and `!`.
+    Address,
+}
+
+impl ExpressionKind{
+    /// Parses a `NUMBER` token such as `42`, `-1i64`, `0xFF_u8` or `0b1010`.
+    pub fn number(literal: &str) -> Result<ExpressionKind, &'static str>{
+        let (digits, suffix) = match literal.find(|c| c == 'i' || c == 'u'){
+            Some(i) => match SimaType::basic(&literal[i..])?{
+                SimaType::Basic{kind, width} => (&literal[..i], Some((kind, width))),
+                _ => unreachable!(),
+            },
+            None => (literal, None),
+        };
+        let (negative, digits) = match digits.starts_with('-'){
+            true => (true, &digits[1..]),
+            false => (false, digits),
+        };
+        let (radix, digits) = match digits.get(..2){
+            Some("0x") => (16, &digits[2..]),
+            Some("0b") => (2, &digits[2..]),
+            _ => (10, digits),
+        };
+        let digits: String = digits.chars().filter(|&c| c != '_').collect();
+        let value = i128::from_str_radix(&digits, radix).ok()
+            .map(|v| if negative { -v } else { v });
+        Ok(ExpressionKind::Number{literal: literal.to_string(), value, suffix, typ: Cell::new(None)})
+    }
+    /// Parses a `CHARACTER` token such as `'a'` or `'\n'`, which is an
+    /// integer literal holding the character's code point, or the byte of a
+    /// `\x` escape.
+    pub fn character(literal: &str) -> Result<ExpressionKind, &'static str>{
+        let inner = &literal[1..literal.len() - 1];
+        let bytes = unescape(inner)?;
+        let value = match inner.starts_with("\\x"){
+            true => bytes[0] as i128,
+            false => str::from_utf8(&bytes).map_err(|_| "invalid character literal")?
+                .chars().next().unwrap() as i128,
+        };
+        Ok(ExpressionKind::Number{literal: literal.to_string(), value: Some(value), suffix: None, typ: Cell::new(None)})
+    }
+    /// Parses a `FIELD` token such as `Point.x`, `Point.x!` or `Point->x`.
+    pub fn field(token: &str) -> ExpressionKind{
+        let (record, rest, access) = match token.find("->"){
+            Some(arrow) => (&token[..arrow], &token[arrow + 2..], FieldAccess::Address),
+            None => {
+                let dot = token.find('.').unwrap();
+                match token.ends_with('!'){
+                    true => (&token[..dot], &token[dot + 1..token.len() - 1], FieldAccess::Set),
+                    false => (&token[..dot], &token[dot + 1..], FieldAccess::Get),
+                }
+            },
+        };
+        ExpressionKind::Field{record: record.to_string(), field: rest.to_string(), access}
+    }
+    /// Parses a `STRINGLITERAL`, `RAWSTRING` or `BYTESTRING` token.
+    pub fn string(literal: &str) -> Result<ExpressionKind, &'static str>{
+        let (raw, byte_string, inner) = match &literal[..1]{
+            "r" => (true, false, &literal[2..literal.len() - 1]),
+            "b" => (false, true, &literal[2..literal.len() - 1]),
+            _ => (false, false, &literal[1..literal.len() - 1]),
+        };
+        let decoded = match raw{
+            true => inner.as_bytes().to_vec(),
+            false => unescape(inner)?,
+        };
+        Ok(match byte_string{
+            true => ExpressionKind::ByteString{literal: literal.to_string(), bytes: decoded},
+            false => ExpressionKind::StringLiteral{literal: literal.to_string(), bytes: decoded},
+        })
+    }
+}
+
+/// Decodes the escape sequences between the quotes of a literal. The lexer
+/// only lets valid escapes through, but `\u{...}` may still name something
+/// that is not a character. A backslash at the end of a line skips the line
+/// break and the indentation of the next line.
+fn unescape(text: &str) -> Result<Vec<u8>, &'static str>{
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    let mut buffer = [0; 4];
+    while let Some(c) = chars.next(){
+        if c != '\\'{
+            bytes.extend(c.encode_utf8(&mut buffer).as_bytes());
+            continue;
+        }
+        match chars.next().unwrap(){
+            'n' => bytes.push(b'\n'),
+            'r' => bytes.push(b'\r'),
+            't' => bytes.push(b'\t'),
+            '0' => bytes.push(0),
+            'x' => {
+                let digits: String = chars.by_ref().take(2).collect();
+                bytes.push(u8::from_str_radix(&digits, 16).unwrap());
+            },
+            'u' => {
+                let digits: String = chars.by_ref().skip(1).take_while(|&c| c != '}').collect();
+                let c = u32::from_str_radix(&digits, 16).ok().and_then(char::from_u32)
+                    .ok_or("invalid unicode escape")?;
+                bytes.extend(c.encode_utf8(&mut buffer).as_bytes());
+            },
+            '\n' => while chars.peek().map_or(false, |&c| c == ' ' || c == '\t'){
+                chars.next();
+            },
+            other => bytes.push(other as u8),
+        }
+    }
+    Ok(bytes)
 }
 
 impl Expression{
+    pub fn new(kind: ExpressionKind, span: Span) -> Expression{
+        Expression{kind, span}
+    }
     pub fn in_ariety(&self) -> usize{
-        use self::Expression::*;
-        use std::cmp::max;
-        match *self{
+        use self::ExpressionKind::*;
+        use std::cmp::{min, max};
+        match self.kind{
             Concat{ref left, ref right} => {
                 let l = left.in_ariety();
                 let r = right.in_ariety();
-                l+r-max(r, left.out_ariety())
+                l+r-min(r, left.out_ariety())
             },
             Sidecat{ref left, ref right} => {
                 left.in_ariety() + right.in_ariety()
             },
-            Identifier{ref in_ariety, ..} => in_ariety.get(),
-            Block{..} | StringLiteral(_) | Number(_) => 0,
-            Duplicate | Discard | Keep => 1,
-            Exchange => 2,
+            Identifier{ref in_ariety, ..} | Apply{ref in_ariety, ..} |
+            Conditional{ref in_ariety, ..} | Match{ref in_ariety, ..} => in_ariety.get(),
+            Case{ref payload, ..} => payload.get(),
+            Pack(ref typ) => typ.elements().map_or(0, |(_, length)| length as usize),
+            While{ref condition, ref body} => max(condition.get().in_ariety, body.get().in_ariety) + 2,
+            Times{ref body} => body.get().in_ariety + 2,
+            Block{..} | StringLiteral{..} | ByteString{..} | Number{..} | Float{..} |
+            SizeOf(_) | AlignOf(_) => 0,
+            Duplicate | Discard | Keep | Negate | Load | Cast(_) => 1,
+            Field{access: FieldAccess::Set, ..} => 2,
+            Field{..} => 1,
+            Exchange | Arithmetic(_) | Comparison(_) | Store | Offset | Index | Extract => 2,
+            Insert => 3,
         }
     }
     pub fn out_ariety(&self) -> usize{
-        use self::Expression::*;
-        use std::cmp::max;
-        match *self{
+        use self::ExpressionKind::*;
+        use std::cmp::{min, max};
+        match self.kind{
             Concat{ref left, ref right} => {
                 let l = left.out_ariety();
                 let r = right.out_ariety();
-                l+r-max(l, right.in_ariety())
+                l+r-min(l, right.in_ariety())
             },
             Sidecat{ref left, ref right} => {
                 left.out_ariety() + right.out_ariety()
             },
-            Identifier{ref out_ariety, ..} => out_ariety.get(),
-            Discard => 0,
-            Block{..} | StringLiteral(_) | Number(_) | Keep => 1,
-            Duplicate | Exchange => 2,
+            Identifier{ref out_ariety, ..} | Apply{ref out_ariety, ..} |
+            Conditional{ref out_ariety, ..} | Match{ref out_ariety, ..} => out_ariety.get(),
+            While{ref condition, ref body} => max(condition.get().in_ariety, body.get().in_ariety),
+            Times{ref body} => body.get().in_ariety,
+            Discard | Store => 0,
+            Block{..} | StringLiteral{..} | Number{..} | Float{..} | Keep => 1,
+            Arithmetic(_) | Negate | Comparison(_) => 1,
+            Load | Offset | Index | SizeOf(_) | AlignOf(_) | Cast(_) | Field{..} | Case{..} => 1,
+            Pack(_) | Extract | Insert => 1,
+            Duplicate | Exchange | ByteString{..} => 2,
         }
     }
-    pub fn calc_ariety(&self, module: &Module){
-        use self::Expression::*;
-        match *self{
-            Identifier{ref id, ref in_ariety, ref out_ariety} => { 
-                let f = module.functions.get(id);
-                assert!(f.is_some(), "Identifier '{}' is not in Scope of Module '{}'", id, module.id);
-                let f = f.unwrap();
-                in_ariety.set(f.in_ariety());
+    /// The stack effect of the code inside a quotation, as opposed to the
+    /// effect of pushing the quotation itself.
+    pub fn block_effect(&self) -> Option<StackEffect>{
+        match self.kind{
+            ExpressionKind::Block{ref inner} => Some(StackEffect{
+                in_ariety: inner.in_ariety(),
+                out_ariety: inner.out_ariety(),
+            }),
+            _ => None,
+        }
+    }
+    /// Resolves the arieties of identifiers and `call`s. `stack` mirrors the
+    /// data stack and records the effect of every quotation whose effect is
+    /// statically known, so that `call` can tell how many values it consumes.
+    pub fn calc_ariety(&self, module: &Module, stack: &mut Vec<Option<StackEffect>>) -> Result<(), Diagnostic>{
+        use self::ExpressionKind::*;
+        match self.kind{
+            Identifier{ref id, extra, ref in_ariety, ref out_ariety} => { 
+                let f = match module.functions.get(id){
+                    Some(f) => f,
+                    None => match module.types.get(id).map(|named| &named.definition){
+                        // the constructor of a record
+                        Some(&TypeDefinition::Record{ref fields}) if extra.is_none() => {
+                            in_ariety.set(fields.len());
+                            out_ariety.set(1);
+                            drop_effects(stack, fields.len());
+                            stack.push(None);
+                            return Ok(());
+                        },
+                        _ => return Err(Diagnostic::error(format!("Identifier '{}' is not in Scope of Module '{}'", id, module.id))
+                            .with_primary(self.span, "not declared")),
+                    },
+                };
+                if extra.is_some() && !f.variadic{
+                    return Err(Diagnostic::error(format!("'{}' is not variadic, so it cannot take extra arguments", id))
+                        .with_primary(self.span, "called with a number of extra arguments")
+                        .with_secondary(f.span, "declared without '...' here"));
+                }
+                let takes = f.in_ariety() + extra.unwrap_or(0);
+                in_ariety.set(takes);
                 out_ariety.set(f.out_ariety());
+                drop_effects(stack, takes);
+                stack.extend(f.ret_effects());
             },
-            Concat{ref left, ref right} | Sidecat{ref left, ref right} => {
-                left.calc_ariety(module);
-                right.calc_ariety(module);
+            Apply{ref in_ariety, ref out_ariety} => {
+                let effect = self.known_effect(pop_effect(stack), "call")?;
+                in_ariety.set(effect.in_ariety + 1);
+                out_ariety.set(effect.out_ariety);
+                drop_effects(stack, effect.in_ariety);
+                stack.extend(vec![None; effect.out_ariety]);
             },
-            _ => {},           
+            Conditional{ref in_ariety, ref out_ariety} => {
+                let otherwise = self.known_effect(pop_effect(stack), "if")?;
+                let then = self.known_effect(pop_effect(stack), "if")?;
+                if then != otherwise{
+                    return Err(Diagnostic::error("The branches of 'if' have different stack effects")
+                        .with_primary(self.span, format!("the first branch has stack effect {}, the second {}", then, otherwise)));
+                }
+                in_ariety.set(then.in_ariety + 3);
+                out_ariety.set(then.out_ariety);
+                drop_effects(stack, then.in_ariety + 1);
+                stack.extend(vec![None; then.out_ariety]);
+            },
+            While{ref condition, ref body} => {
+                let body_effect = self.known_effect(pop_effect(stack), "while")?;
+                let condition_effect = self.known_effect(pop_effect(stack), "while")?;
+                if condition_effect.out_ariety != condition_effect.in_ariety + 1{
+                    return Err(Diagnostic::error("The condition of 'while' must leave exactly one value more than it takes")
+                        .with_primary(self.span, format!("the condition has stack effect {}", condition_effect)));
+                }
+                if body_effect.out_ariety != body_effect.in_ariety{
+                    return Err(Diagnostic::error("The body of 'while' must leave as many values as it takes")
+                        .with_primary(self.span, format!("the body has stack effect {}", body_effect)));
+                }
+                condition.set(condition_effect);
+                body.set(body_effect);
+                let carried = self.out_ariety();
+                drop_effects(stack, carried);
+                stack.extend(vec![None; carried]);
+            },
+            Times{ref body} => {
+                let body_effect = self.known_effect(pop_effect(stack), "times")?;
+                if body_effect.out_ariety != body_effect.in_ariety{
+                    return Err(Diagnostic::error("The body of 'times' must leave as many values as it takes")
+                        .with_primary(self.span, format!("the body has stack effect {}", body_effect)));
+                }
+                body.set(body_effect);
+                drop_effects(stack, body_effect.in_ariety + 1);
+                stack.extend(vec![None; body_effect.in_ariety]);
+            },
+            Concat{ref left, ref right} => {
+                left.calc_ariety(module, stack)?;
+                right.calc_ariety(module, stack)?;
+            },
+            Sidecat{ref left, ref right} => {
+                // right works on the top of the stack and left below it, so
+                // right's in_ariety has to be known before the stack is split
+                right.calc_ariety(module, &mut stack.clone())?;
+                let split = stack.len().saturating_sub(right.in_ariety());
+                let mut top = stack.split_off(split);
+                left.calc_ariety(module, stack)?;
+                right.calc_ariety(module, &mut top)?;
+                stack.append(&mut top);
+            },
+            Block{ref inner} => {
+                inner.calc_ariety(module, &mut Vec::new())?;
+                stack.push(self.block_effect());
+            },
+            StringLiteral{..} | Number{..} | Float{..} => stack.push(None),
+            SizeOf(ref typ) | AlignOf(ref typ) => {
+                module.check_type(typ, self.span)?;
+                stack.push(None);
+            },
+            ByteString{..} => stack.extend(vec![None; 2]),
+            Duplicate => {
+                let top = pop_effect(stack);
+                stack.push(top);
+                stack.push(top);
+            },
+            Discard => {
+                stack.pop();
+            },
+            Exchange => {
+                let first = pop_effect(stack);
+                let second = pop_effect(stack);
+                stack.push(first);
+                stack.push(second);
+            },
+            Keep => {},
+            Arithmetic(_) | Comparison(_) | Offset | Index | Extract => {
+                drop_effects(stack, 2);
+                stack.push(None);
+            },
+            Insert => {
+                drop_effects(stack, 3);
+                stack.push(None);
+            },
+            Pack(ref typ) => {
+                module.check_type(typ, self.span)?;
+                let length = match typ.elements(){
+                    Some((_, length)) => length as usize,
+                    None => return Err(Diagnostic::error(format!("'pack' makes arrays and vectors, not {}", typ))
+                        .with_primary(self.span, "needs an array or vector type")),
+                };
+                drop_effects(stack, length);
+                stack.push(None);
+            },
+            Store => drop_effects(stack, 2),
+            Cast(ref typ) => {
+                module.check_type(typ, self.span)?;
+                stack.pop();
+                stack.push(None);
+            },
+            Field{ref record, ref field, access} => {
+                let known = module.types.get(record)
+                    .map_or(false, |named| named.definition.field(field).is_some());
+                if !known{
+                    return Err(Diagnostic::error(format!("Record '{}' has no field '{}'", record, field))
+                        .with_primary(self.span, "no such record field"));
+                }
+                drop_effects(stack, if access == FieldAccess::Set { 2 } else { 1 });
+                stack.push(None);
+            },
+            Case{ref variant, ref case, ref payload} => {
+                let count = match module.types.get(variant).and_then(|named| named.definition.case(case)){
+                    Some((_, types)) => types.len(),
+                    None => return Err(Diagnostic::error(format!("Variant '{}' has no case '{}'", variant, case))
+                        .with_primary(self.span, "no such variant case")),
+                };
+                payload.set(count);
+                drop_effects(stack, count);
+                stack.push(None);
+            },
+            Match{ref variant, ref cases, ref in_ariety, ref out_ariety} => {
+                let effect = self.match_effect(module, variant, cases)?;
+                in_ariety.set(effect.in_ariety + 1);
+                out_ariety.set(effect.out_ariety);
+                drop_effects(stack, effect.in_ariety + 1);
+                stack.extend(vec![None; effect.out_ariety]);
+            },
+            Negate | Load => {
+                stack.pop();
+                stack.push(None);
+            },
+        }
+        Ok(())
+    }
+    /// The stack effect every case of a `match` has to share, counting the
+    /// payload a case gets as values it takes from the stack.
+    fn match_effect(&self, module: &Module, variant: &str, cases: &[MatchCase]) -> Result<StackEffect, Diagnostic>{
+        use std::cmp::min;
+        let declared = match module.types.get(variant).map(|named| &named.definition){
+            Some(&TypeDefinition::Variant{ref cases}) => cases,
+            _ => return Err(Diagnostic::error(format!("'{}' is not a variant", variant))
+                .with_primary(self.span, "matched here")),
+        };
+        let mut first: Option<(StackEffect, &MatchCase)> = None;
+        for (index, arm) in cases.iter().enumerate(){
+            let payload = match declared.iter().find(|&&(ref id, _)| *id == arm.case){
+                Some(&(_, ref payload)) => payload.len(),
+                None => return Err(Diagnostic::error(format!("Variant '{}' has no case '{}'", variant, arm.case))
+                    .with_primary(arm.span, "no such variant case")),
+            };
+            if cases[..index].iter().any(|other| other.case == arm.case){
+                return Err(Diagnostic::error(format!("Case '{}' is matched more than once", arm.case))
+                    .with_primary(arm.span, "matched again here"));
+            }
+            arm.body.calc_ariety(module, &mut Vec::new())?;
+            let body_in = arm.body.in_ariety();
+            let effect = StackEffect{
+                in_ariety: body_in - min(body_in, payload),
+                out_ariety: payload + arm.body.out_ariety() - min(payload, body_in),
+            };
+            if let Some((expected, other)) = first{
+                if expected != effect{
+                    return Err(Diagnostic::error("The cases of 'match' have different stack effects")
+                        .with_primary(arm.span, format!("'{}' has stack effect {}", arm.case, effect))
+                        .with_secondary(other.span, format!("'{}' has stack effect {}", other.case, expected)));
+                }
+            }else{
+                first = Some((effect, arm));
+            }
+        }
+        if let Some(&(ref missing, _)) = declared.iter().find(|&&(ref id, _)| cases.iter().all(|arm| arm.case != *id)){
+            return Err(Diagnostic::error(format!("Case '{}' of variant '{}' is not matched", missing, variant))
+                .with_primary(self.span, format!("'match' is missing case '{}'", missing)));
+        }
+        Ok(first.map(|(effect, _)| effect).unwrap_or_default())
+    }
+    fn known_effect(&self, effect: Option<StackEffect>, word: &str) -> Result<StackEffect, Diagnostic>{
+        effect.ok_or_else(|| Diagnostic::error(format!("The stack effect of the quotation used by '{}' is not known", word))
+            .with_primary(self.span, "only quotations written in place or declared as arguments can be used here"))
+    }
+}
+
+/// Prints an expression the way it could be written in a source file.
+impl fmt::Display for Expression{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+        use self::ExpressionKind::*;
+        match self.kind{
+            Concat{ref left, ref right} => write!(f, "{} {}", left, right),
+            Sidecat{ref left, ref right} => {
+                match left.kind{
+                    Concat{..} => write!(f, "({})", left)?,
+                    _ => write!(f, "{}", left)?,
+                }
+                match right.kind{
+                    Concat{..} | Sidecat{..} => write!(f, ", ({})", right),
+                    _ => write!(f, ", {}", right),
+                }
+            },
+            Block{ref inner} => write!(f, "[{}]", inner),
+            StringLiteral{ref literal, ..} | ByteString{ref literal, ..} |
+            Number{ref literal, ..} | Float{ref literal, ..} => f.write_str(literal),
+            Identifier{ref id, extra: Some(extra), ..} => write!(f, "{}/{}", id, extra),
+            Identifier{ref id, extra: None, ..} => f.write_str(id),
+            Apply{..} => f.write_str("call"),
+            Conditional{..} => f.write_str("if"),
+            While{..} => f.write_str("while"),
+            Times{..} => f.write_str("times"),
+            Arithmetic(operator) => write!(f, "{}", operator),
+            Negate => f.write_str("neg"),
+            Comparison(comparison) => write!(f, "{}", comparison),
+            Duplicate => f.write_str("dup"),
+            Discard => f.write_str("drop"),
+            Exchange => f.write_str("swap"),
+            Keep => f.write_str("id"),
+            Load => f.write_str("@"),
+            Store => f.write_str("!"),
+            Offset => f.write_str("+ptr"),
+            Index => f.write_str("index"),
+            SizeOf(ref typ) => write!(f, "sizeof<{}>", typ),
+            AlignOf(ref typ) => write!(f, "alignof<{}>", typ),
+            Field{ref record, ref field, access: FieldAccess::Get} => write!(f, "{}.{}", record, field),
+            Field{ref record, ref field, access: FieldAccess::Set} => write!(f, "{}.{}!", record, field),
+            Field{ref record, ref field, access: FieldAccess::Address} => write!(f, "{}->{}", record, field),
+            Case{ref variant, ref case, ..} => write!(f, "{}::{}", variant, case),
+            Pack(ref typ) => write!(f, "pack<{}>", typ),
+            Extract => f.write_str("extract"),
+            Insert => f.write_str("insert"),
+            Match{ref variant, ref cases, ..} => {
+                write!(f, "match {} {{", variant)?;
+                for (index, arm) in cases.iter().enumerate(){
+                    let separator = if index == 0 { " " } else { ", " };
+                    write!(f, "{}{} [{}]", separator, arm.case, arm.body)?;
+                }
+                f.write_str(" }")
+            },
+            Cast(ref typ @ SimaType::Basic{..}) => write!(f, ">{}", typ),
+            Cast(ref typ) => write!(f, "as<{}>", typ),
         }
     }
 }
 
+/// Built-in binary arithmetic words.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator{
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+}
+impl fmt::Display for Operator{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+        use self::Operator::*;
+        f.write_str(match *self{
+            Add => "+",
+            Subtract => "-",
+            Multiply => "*",
+            Divide => "/",
+            Remainder => "mod",
+        })
+    }
+}
+
+/// Built-in comparison words, each leaving an `i1` on the stack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison{
+    Equal,
+    NotEqual,
+    Less,
+    Greater,
+    LessEqual,
+    GreaterEqual,
+}
+impl fmt::Display for Comparison{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+        use self::Comparison::*;
+        f.write_str(match *self{
+            Equal => "=",
+            NotEqual => "!=",
+            Less => "<",
+            Greater => ">",
+            LessEqual => "<=",
+            GreaterEqual => ">=",
+        })
+    }
+}
+
+fn pop_effect(stack: &mut Vec<Option<StackEffect>>) -> Option<StackEffect>{
+    stack.pop().unwrap_or(None)
+}
+
+fn drop_effects(stack: &mut Vec<Option<StackEffect>>, count: usize){
+    let len = stack.len();
+    stack.truncate(len.saturating_sub(count));
+}
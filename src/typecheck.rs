@@ -0,0 +1,758 @@
+use std::fmt;
+use diagnostic::{Diagnostic, Span};
+use source_ast::{Module, Expression, SimaType, BasicKind, TypeDefinition, FieldAccess};
+
+/// Checks every definition of `module` against its declared type, walking
+/// the body with a stack of types. Expects `Module::calc_arieties` to have
+/// run, since the arieties decide how `,` splits the stack.
+pub fn check_module(module: &Module) -> Result<(), Vec<Diagnostic>>{
+    let mut errors = Vec::new();
+    for (id, fun) in module.iter(){
+        if let Some(ref expr) = fun.expr{
+            let mut checker = Checker::new(module, id, Some(fun.span));
+            checker.check_definition(expr, &fun.typ);
+            errors.extend(checker.errors);
+        }
+    }
+    if errors.is_empty(){
+        Ok(())
+    }else{
+        Err(errors)
+    }
+}
+
+/// Checks an expression that runs on top of a stack of values with the given
+/// types, like a line typed into the REPL. Returns the types of the stack
+/// afterwards. Expects the arieties of `expr` to be calculated.
+pub fn check_expression<'a>(module: &'a Module, stack: &[SimaType], expr: &'a Expression) -> Result<Vec<SimaType>, Vec<Diagnostic>>{
+    let mut checker = Checker::new(module, "", None);
+    let mut types = Stack{values: stack.iter().map(Type::from).collect(), inputs: Vec::new()};
+    checker.check(expr, &mut types);
+    checker.type_literals();
+    checker.check_casts();
+    if !types.inputs.is_empty(){
+        let label = format!("takes {} more values than there are on the stack", types.inputs.len());
+        checker.error(expr, "stack underflow", label);
+    }
+    let mut result = Vec::with_capacity(types.values.len());
+    for typ in types.values.iter(){
+        match checker.sima_type(typ){
+            Some(typ) => result.push(typ),
+            None => {
+                let label = format!("leaves a value of unknown type {} on the stack", checker.resolve(typ));
+                checker.error(expr, "type annotations needed", label);
+            },
+        }
+    }
+    if checker.errors.is_empty(){
+        Ok(result)
+    }else{
+        Err(checker.errors)
+    }
+}
+
+/// A `SimaType` that may still contain unknown parts, e.g. the inputs of a
+/// quotation or the values `dup` and `swap` work on.
+#[derive(Debug, Clone, PartialEq)]
+enum Type{
+    Var(usize),
+    Basic{kind: BasicKind, width: u32},
+    Pointer(Box<Type>),
+    Function{args: Vec<Type>, ret: Vec<Type>},
+    Opaque,
+    /// Named types are equal only to themselves.
+    Named(String),
+    Array(Box<Type>, u32),
+    Vector(Box<Type>, u32),
+}
+
+impl<'a> From<&'a SimaType> for Type{
+    fn from(typ: &'a SimaType) -> Type{
+        match *typ{
+            SimaType::Function{ref args, ref ret} => Type::Function{
+                args: args.iter().map(Type::from).collect(),
+                ret: ret.iter().map(Type::from).collect(),
+            },
+            SimaType::Pointer{ref target} => Type::Pointer(Box::new(Type::from(&**target))),
+            SimaType::Basic{kind, width} => Type::Basic{kind, width},
+            SimaType::Opaque => Type::Opaque,
+            SimaType::Named(ref id) => Type::Named(id.clone()),
+            SimaType::Array{ref element, length} => Type::Array(Box::new(Type::from(&**element)), length),
+            SimaType::Vector{ref element, length} => Type::Vector(Box::new(Type::from(&**element)), length),
+        }
+    }
+}
+
+impl fmt::Display for Type{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+        match *self{
+            Type::Var(_) => f.write_str("_"),
+            Type::Basic{kind, width} => write!(f, "{}{}", kind, width),
+            Type::Pointer(ref target) => write!(f, "{}*", target),
+            Type::Function{ref args, ref ret} => {
+                f.write_str("[")?;
+                for arg in args{
+                    write!(f, "{} ", arg)?;
+                }
+                f.write_str("->")?;
+                for r in ret{
+                    write!(f, " {}", r)?;
+                }
+                f.write_str("]")
+            },
+            Type::Opaque => f.write_str("opaque"),
+            Type::Named(ref id) => f.write_str(id),
+            Type::Array(ref element, length) => write!(f, "[{}; {}]", element, length),
+            Type::Vector(ref element, length) => write!(f, "<{} x {}>", length, element),
+        }
+    }
+}
+
+fn bool_type() -> Type{
+    Type::Basic{kind: BasicKind::Signed, width: 1}
+}
+
+/// The types a piece of code leaves on the stack. Values it takes from below
+/// its own start are recorded in `inputs`, deepest first.
+#[derive(Debug, Default)]
+struct Stack{
+    values: Vec<Type>,
+    inputs: Vec<Type>,
+}
+
+struct Checker<'a>{
+    module: &'a Module,
+    function: &'a str,
+    /// Where the checked function is declared, if the code belongs to one.
+    declaration: Option<Span>,
+    bindings: Vec<Option<Type>>,
+    /// Integer and float literals, with the type they were given.
+    literals: Vec<(&'a Expression, Type)>,
+    /// Casts with the type of the value they convert, which is checked last
+    /// so that literals have their types by then.
+    casts: Vec<(&'a Expression, Type)>,
+    errors: Vec<Diagnostic>,
+}
+
+impl<'a> Checker<'a>{
+    fn new(module: &'a Module, function: &'a str, declaration: Option<Span>) -> Checker<'a>{
+        Checker{
+            module,
+            function,
+            declaration,
+            bindings: Vec::new(),
+            literals: Vec::new(),
+            casts: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    fn check_definition(&mut self, expr: &'a Expression, typ: &SimaType){
+        let (args, ret) = match Type::from(typ){
+            Type::Function{args, ret} => (args, ret),
+            other => {
+                let message = format!("'{}' is defined, but declared with type {}, which is not a function type", self.function, other);
+                self.error(expr, message, "defined here");
+                return;
+            }
+        };
+        let mut stack = Stack{values: args, inputs: Vec::new()};
+        self.check(expr, &mut stack);
+        if !stack.inputs.is_empty(){
+            let message = format!("'{}' takes {} more values than its declared type provides", self.function, stack.inputs.len());
+            self.error(expr, message, "this definition");
+        }
+        if stack.values.len() != ret.len(){
+            let message = format!("'{}' is declared to return {} values", self.function, ret.len());
+            let label = format!("this definition leaves {} values on the stack", stack.values.len());
+            self.error(expr, message, label);
+        }else{
+            for (expected, found) in ret.iter().zip(stack.values.iter()){
+                self.expect(expr, expected, found);
+            }
+        }
+        self.type_literals();
+        self.check_casts();
+    }
+
+    fn check(&mut self, expr: &'a Expression, stack: &mut Stack){
+        use source_ast::ExpressionKind::*;
+        match expr.kind{
+            Concat{ref left, ref right} => {
+                self.check(left, stack);
+                self.check(right, stack);
+            },
+            Sidecat{ref left, ref right} => {
+                let values = self.pop_n(stack, right.in_ariety());
+                self.check(left, stack);
+                let mut top = Stack{values, inputs: Vec::new()};
+                self.check(right, &mut top);
+                stack.values.extend(top.values);
+            },
+            Block{ref inner} => {
+                let mut inner_stack = Stack::default();
+                self.check(inner, &mut inner_stack);
+                stack.values.push(Type::Function{args: inner_stack.inputs, ret: inner_stack.values});
+            },
+            StringLiteral{..} => {
+                let byte = Type::Basic{kind: BasicKind::Signed, width: 8};
+                stack.values.push(Type::Pointer(Box::new(byte)));
+            },
+            ByteString{..} => {
+                let byte = Type::Basic{kind: BasicKind::Signed, width: 8};
+                stack.values.push(Type::Pointer(Box::new(byte)));
+                stack.values.push(Type::Basic{kind: BasicKind::Unsigned, width: 64});
+            },
+            Number{suffix, ..} => {
+                // every literal gets a variable of its own, which tells
+                // constant indices apart from other values
+                let typ = self.fresh();
+                if let Some((kind, width)) = suffix{
+                    self.unify(&Type::Basic{kind, width}, &typ);
+                }
+                self.literals.push((expr, typ.clone()));
+                stack.values.push(typ);
+            },
+            Float{..} => {
+                let typ = self.fresh();
+                self.literals.push((expr, typ.clone()));
+                stack.values.push(typ);
+            },
+            Identifier{ref id, extra, ..} => {
+                let typ = match self.module.functions.get(id){
+                    Some(f) => Type::from(&f.typ),
+                    // the constructor of a record takes its fields
+                    None => match self.module.types[id].definition{
+                        TypeDefinition::Record{ref fields} => Type::Function{
+                            args: fields.iter().map(|&(_, ref t)| Type::from(t)).collect(),
+                            ret: vec![Type::Named(id.clone())],
+                        },
+                        TypeDefinition::Opaque | TypeDefinition::Variant{..} => unreachable!(),
+                    },
+                };
+                match typ{
+                    Type::Function{mut args, ret} => {
+                        // the extra arguments of a variadic call can have
+                        // any type C knows about
+                        let extras: Vec<Type> = (0..extra.unwrap_or(0)).map(|_| self.fresh()).collect();
+                        args.extend(extras.iter().cloned());
+                        self.call(expr, stack, &args, &ret);
+                        for typ in extras.iter(){
+                            if let Type::Function{..} = self.shallow(typ){
+                                let label = format!("passes a quotation of type {} as an extra argument", self.resolve(typ));
+                                self.error(expr, "quotations cannot be passed to variadic functions", label);
+                                continue;
+                            }
+                            // the same rules as for the declared arguments of
+                            // C functions
+                            match self.sima_type(typ){
+                                Some(SimaType::Named(id)) => {
+                                    let message = format!("C functions cannot take {} by value", id);
+                                    self.error(expr, message, "passed as an extra argument here");
+                                },
+                                Some(ref other) if !other.is_c_compatible() => {
+                                    let message = format!("C functions cannot take {}", other);
+                                    self.error(expr, message, "only vectors of 16 bytes with a power of two elements can be passed to C");
+                                },
+                                _ => {},
+                            }
+                        }
+                    },
+                    other => {
+                        let message = format!("'{}' has type {}, which is not a function type", id, other);
+                        self.error(expr, message, "used as a word here");
+                    },
+                }
+            },
+            Apply{ref in_ariety, ..} => {
+                let quotation = self.pop(stack);
+                let (args, ret) = self.quotation_type(in_ariety.get() - 1, expr.out_ariety());
+                self.expect(expr, &Type::Function{args: args.clone(), ret: ret.clone()}, &quotation);
+                self.call(expr, stack, &args, &ret);
+            },
+            Conditional{ref in_ariety, ref out_ariety} => {
+                let otherwise = self.pop(stack);
+                let then = self.pop(stack);
+                let condition = self.pop(stack);
+                // a condition that could be any integer is an `i32`, like
+                // other literals, so that any number can be given as one
+                self.expect_integer(expr, &condition, Type::Basic{kind: BasicKind::Signed, width: 32});
+                let (args, ret) = self.quotation_type(in_ariety.get() - 3, out_ariety.get());
+                let typ = Type::Function{args: args.clone(), ret: ret.clone()};
+                self.expect(expr, &typ, &then);
+                self.expect(expr, &typ, &otherwise);
+                self.call(expr, stack, &args, &ret);
+            },
+            While{ref condition, ref body} => {
+                let body_quotation = self.pop(stack);
+                let condition_quotation = self.pop(stack);
+                let carried = self.pop_n(stack, expr.out_ariety());
+                // the condition and the body both have to leave the values
+                // they work on with the types they found them with
+                let condition_args = carried[carried.len() - condition.get().in_ariety..].to_vec();
+                let flag = self.fresh();
+                let mut condition_ret = condition_args.clone();
+                condition_ret.push(flag.clone());
+                self.expect(expr, &Type::Function{args: condition_args, ret: condition_ret}, &condition_quotation);
+                self.expect_integer(expr, &flag, Type::Basic{kind: BasicKind::Signed, width: 32});
+                let body_args = carried[carried.len() - body.get().in_ariety..].to_vec();
+                self.expect(expr, &Type::Function{args: body_args.clone(), ret: body_args}, &body_quotation);
+                stack.values.extend(carried);
+            },
+            Times{ref body} => {
+                let body_quotation = self.pop(stack);
+                let carried = self.pop_n(stack, body.get().in_ariety);
+                let count = self.pop(stack);
+                self.expect_integer(expr, &count, Type::Basic{kind: BasicKind::Signed, width: 32});
+                self.expect(expr, &Type::Function{args: carried.clone(), ret: carried.clone()}, &body_quotation);
+                stack.values.extend(carried);
+            },
+            Arithmetic(_) | Comparison(_) => {
+                let right = self.pop(stack);
+                let left = self.pop(stack);
+                self.expect(expr, &left, &right);
+                match expr.kind{
+                    Comparison(_) => {
+                        self.expect_number(expr, &left);
+                        stack.values.push(bool_type());
+                    },
+                    _ => {
+                        self.expect_numbers(expr, &left);
+                        stack.values.push(left);
+                    },
+                }
+            },
+            Negate => {
+                let operand = self.pop(stack);
+                self.expect_numbers(expr, &operand);
+                stack.values.push(operand);
+            },
+            Duplicate => {
+                let top = self.pop(stack);
+                stack.values.push(top.clone());
+                stack.values.push(top);
+            },
+            Discard => {
+                self.pop(stack);
+            },
+            Exchange => {
+                let first = self.pop(stack);
+                let second = self.pop(stack);
+                stack.values.push(first);
+                stack.values.push(second);
+            },
+            Keep => {},
+            Load => {
+                let pointer = self.pop(stack);
+                let target = self.pointee(expr, &pointer);
+                stack.values.push(target);
+            },
+            Store => {
+                let pointer = self.pop(stack);
+                let value = self.pop(stack);
+                let target = self.pointee(expr, &pointer);
+                self.expect(expr, &target, &value);
+            },
+            Offset | Index => {
+                let offset = self.pop(stack);
+                self.expect_integer(expr, &offset, Type::Basic{kind: BasicKind::Signed, width: 64});
+                let pointer = self.pop(stack);
+                let target = self.pointee(expr, &pointer);
+                match expr.kind{
+                    Index => stack.values.push(target),
+                    _ => stack.values.push(pointer),
+                }
+            },
+            // `Module::check_type` already rejected opaque types here
+            SizeOf(_) | AlignOf(_) => stack.values.push(Type::Basic{kind: BasicKind::Unsigned, width: 64}),
+            Field{ref record, ref field, access} => {
+                let field_type = Type::from(self.module.types[record].definition.field(field).unwrap().1);
+                let named = Type::Named(record.clone());
+                match access{
+                    FieldAccess::Get => {
+                        let value = self.pop(stack);
+                        self.expect(expr, &named, &value);
+                        stack.values.push(field_type);
+                    },
+                    FieldAccess::Set => {
+                        let value = self.pop(stack);
+                        let value_record = self.pop(stack);
+                        self.expect(expr, &named, &value_record);
+                        self.expect(expr, &field_type, &value);
+                        stack.values.push(named);
+                    },
+                    FieldAccess::Address => {
+                        let pointer = self.pop(stack);
+                        self.expect(expr, &Type::Pointer(Box::new(named)), &pointer);
+                        stack.values.push(Type::Pointer(Box::new(field_type)));
+                    },
+                }
+            },
+            Case{ref variant, ref case, ..} => {
+                let (_, payload) = self.module.types[variant].definition.case(case).unwrap();
+                let args: Vec<Type> = payload.iter().map(Type::from).collect();
+                self.call(expr, stack, &args, &[Type::Named(variant.clone())]);
+            },
+            Match{ref variant, ref cases, ref in_ariety, ref out_ariety} => {
+                let module = self.module;
+                let value = self.pop(stack);
+                self.expect(expr, &Type::Named(variant.clone()), &value);
+                // every case starts with the same values below its payload
+                // and has to leave values of the same types
+                let below = self.pop_n(stack, in_ariety.get() - 1);
+                let (_, ret) = self.quotation_type(0, out_ariety.get());
+                for arm in cases{
+                    let (_, payload) = module.types[variant].definition.case(&arm.case).unwrap();
+                    let mut case_stack = Stack{values: below.clone(), inputs: Vec::new()};
+                    case_stack.values.extend(payload.iter().map(Type::from));
+                    self.check(&arm.body, &mut case_stack);
+                    for (expected, found) in ret.iter().zip(case_stack.values.iter()){
+                        self.expect(&arm.body, expected, found);
+                    }
+                }
+                stack.values.extend(ret);
+            },
+            Pack(ref typ) => {
+                let (element, length) = typ.elements().unwrap();
+                let args: Vec<Type> = (0..length).map(|_| Type::from(element)).collect();
+                self.call(expr, stack, &args, &[Type::from(typ)]);
+            },
+            Extract => {
+                let index = self.pop(stack);
+                self.expect_integer(expr, &index, Type::Basic{kind: BasicKind::Signed, width: 64});
+                let aggregate = self.pop(stack);
+                let element = self.element(expr, &aggregate);
+                self.check_index(expr, &aggregate, &index);
+                stack.values.push(element);
+            },
+            Insert => {
+                let index = self.pop(stack);
+                self.expect_integer(expr, &index, Type::Basic{kind: BasicKind::Signed, width: 64});
+                let value = self.pop(stack);
+                let aggregate = self.pop(stack);
+                let element = self.element(expr, &aggregate);
+                self.expect(expr, &element, &value);
+                self.check_index(expr, &aggregate, &index);
+                stack.values.push(aggregate);
+            },
+            Cast(ref target) => {
+                let value = self.pop(stack);
+                self.casts.push((expr, value));
+                stack.values.push(Type::from(target));
+            },
+        }
+    }
+
+    fn is_opaque(&self, typ: &Type) -> bool{
+        match self.shallow(typ){
+            Type::Opaque => true,
+            Type::Named(ref id) => match self.module.types[id].definition{
+                TypeDefinition::Opaque => true,
+                TypeDefinition::Record{..} | TypeDefinition::Variant{..} => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// The type `pointer` points to, for words that read, write or step over
+    /// it and so have to know its size.
+    fn pointee(&mut self, expr: &Expression, pointer: &Type) -> Type{
+        let target = self.fresh();
+        self.expect(expr, &Type::Pointer(Box::new(target.clone())), pointer);
+        if self.is_opaque(&target){
+            let label = format!("'{}' needs the size of what the pointer points to", expr);
+            self.error(expr, "opaque values cannot be accessed", label);
+        }
+        target
+    }
+
+    /// The element type of an array or vector, which `extract` and `insert`
+    /// work on.
+    fn element(&mut self, expr: &Expression, aggregate: &Type) -> Type{
+        match self.shallow(aggregate){
+            Type::Array(element, _) | Type::Vector(element, _) => *element,
+            other => {
+                let label = format!("expected an array or a vector, found {}", self.resolve(&other));
+                self.error(expr, format!("'{}' expects an array or a vector", expr), label);
+                self.fresh()
+            },
+        }
+    }
+
+    /// Rejects an integer literal used as an index past the end of
+    /// `aggregate`, which the interpreter would only notice when it runs
+    /// and compiled code not at all.
+    fn check_index(&mut self, expr: &Expression, aggregate: &Type, index: &Type){
+        use source_ast::ExpressionKind::Number;
+        let length = match self.shallow(aggregate){
+            Type::Array(_, length) | Type::Vector(_, length) => length,
+            _ => return,
+        };
+        let value = self.literals.iter()
+            .find(|&&(_, ref literal)| literal == index)
+            .and_then(|&(literal, _)| match literal.kind{
+                Number{value, ..} => value,
+                _ => None,
+            });
+        match value{
+            Some(value) if value < 0 || value >= length as i128 => {
+                let label = format!("{} has no element {}", self.resolve(aggregate), value);
+                self.error(expr, "index out of range", label);
+            },
+            _ => {},
+        }
+    }
+
+    /// Takes the arguments of a word with the given signature off the stack
+    /// and pushes its results.
+    fn call(&mut self, expr: &Expression, stack: &mut Stack, args: &[Type], ret: &[Type]){
+        let found = self.pop_n(stack, args.len());
+        for (expected, found) in args.iter().zip(found.iter()){
+            self.expect(expr, expected, found);
+        }
+        stack.values.extend(ret.iter().cloned());
+    }
+
+    /// A quotation type with the given arieties and unknown element types.
+    fn quotation_type(&mut self, in_ariety: usize, out_ariety: usize) -> (Vec<Type>, Vec<Type>){
+        let args = (0..in_ariety).map(|_| self.fresh()).collect();
+        let ret = (0..out_ariety).map(|_| self.fresh()).collect();
+        (args, ret)
+    }
+
+    fn fresh(&mut self) -> Type{
+        self.bindings.push(None);
+        Type::Var(self.bindings.len() - 1)
+    }
+
+    fn pop(&mut self, stack: &mut Stack) -> Type{
+        match stack.values.pop(){
+            Some(typ) => typ,
+            None => {
+                let typ = self.fresh();
+                stack.inputs.insert(0, typ.clone());
+                typ
+            },
+        }
+    }
+
+    /// Pops `count` types, returning them in the order they were pushed.
+    fn pop_n(&mut self, stack: &mut Stack, count: usize) -> Vec<Type>{
+        let mut values = Vec::with_capacity(count);
+        for _ in 0..count{
+            values.push(self.pop(stack));
+        }
+        values.reverse();
+        values
+    }
+
+    /// Follows the bindings of a type variable until it is either unbound or
+    /// something else.
+    fn shallow(&self, typ: &Type) -> Type{
+        match *typ{
+            Type::Var(var) => match self.bindings[var]{
+                Some(ref bound) => self.shallow(bound),
+                None => typ.clone(),
+            },
+            _ => typ.clone(),
+        }
+    }
+
+    fn resolve(&self, typ: &Type) -> Type{
+        match self.shallow(typ){
+            Type::Pointer(target) => Type::Pointer(Box::new(self.resolve(&target))),
+            Type::Array(element, length) => Type::Array(Box::new(self.resolve(&element)), length),
+            Type::Vector(element, length) => Type::Vector(Box::new(self.resolve(&element)), length),
+            Type::Function{args, ret} => Type::Function{
+                args: args.iter().map(|t| self.resolve(t)).collect(),
+                ret: ret.iter().map(|t| self.resolve(t)).collect(),
+            },
+            other => other,
+        }
+    }
+
+    /// The fully known type behind `typ`, if there is one.
+    fn sima_type(&self, typ: &Type) -> Option<SimaType>{
+        Some(match self.shallow(typ){
+            Type::Var(_) => return None,
+            Type::Basic{kind, width} => SimaType::Basic{kind, width},
+            Type::Pointer(target) => SimaType::Pointer{target: Box::new(self.sima_type(&target)?)},
+            Type::Function{args, ret} => SimaType::Function{
+                args: args.iter().map(|t| self.sima_type(t)).collect::<Option<_>>()?,
+                ret: ret.iter().map(|t| self.sima_type(t)).collect::<Option<_>>()?,
+            },
+            Type::Opaque => SimaType::Opaque,
+            Type::Named(id) => SimaType::Named(id),
+            Type::Array(element, length) => SimaType::Array{element: Box::new(self.sima_type(&element)?), length},
+            Type::Vector(element, length) => SimaType::Vector{element: Box::new(self.sima_type(&element)?), length},
+        })
+    }
+
+    fn occurs(&self, var: usize, typ: &Type) -> bool{
+        match self.shallow(typ){
+            Type::Var(other) => var == other,
+            Type::Pointer(target) | Type::Array(target, _) | Type::Vector(target, _) => self.occurs(var, &target),
+            Type::Function{args, ret} => args.iter().chain(ret.iter()).any(|t| self.occurs(var, t)),
+            _ => false,
+        }
+    }
+
+    fn unify(&mut self, expected: &Type, found: &Type) -> bool{
+        match (self.shallow(expected), self.shallow(found)){
+            (Type::Var(a), Type::Var(b)) if a == b => true,
+            (Type::Var(var), typ) | (typ, Type::Var(var)) => {
+                if self.occurs(var, &typ){
+                    return false;
+                }
+                self.bindings[var] = Some(typ);
+                true
+            },
+            (Type::Pointer(a), Type::Pointer(b)) => self.unify(&a, &b),
+            (Type::Array(a, a_length), Type::Array(b, b_length)) |
+            (Type::Vector(a, a_length), Type::Vector(b, b_length)) => a_length == b_length && self.unify(&a, &b),
+            (Type::Function{args: a_args, ret: a_ret}, Type::Function{args: b_args, ret: b_ret}) => {
+                if a_args.len() != b_args.len() || a_ret.len() != b_ret.len(){
+                    return false;
+                }
+                let mut ok = true;
+                for (a, b) in a_args.iter().zip(b_args.iter()).chain(a_ret.iter().zip(b_ret.iter())){
+                    ok &= self.unify(a, b);
+                }
+                ok
+            },
+            (a, b) => a == b,
+        }
+    }
+
+    fn expect(&mut self, expr: &Expression, expected: &Type, found: &Type){
+        if !self.unify(expected, found){
+            let label = format!("expected {}, found {}", self.resolve(expected), self.resolve(found));
+            self.error(expr, "mismatched types", label);
+        }
+    }
+
+    /// Checks that `found` is an integer, making it `default` if it is not
+    /// known yet.
+    fn expect_integer(&mut self, expr: &Expression, found: &Type, default: Type){
+        match self.shallow(found){
+            Type::Var(_) => {
+                self.unify(&default, found);
+            },
+            Type::Basic{kind, ..} if kind != BasicKind::Float => {},
+            other => {
+                let label = format!("expected an integer, found {}", self.resolve(&other));
+                self.error(expr, format!("'{}' expects an integer", expr), label);
+            },
+        }
+    }
+
+    /// Like `expect_number`, but also accepts vectors of numbers, which
+    /// arithmetic works on element by element.
+    fn expect_numbers(&mut self, expr: &Expression, found: &Type){
+        match self.shallow(found){
+            Type::Vector(element, _) => self.expect_number(expr, &element),
+            _ => self.expect_number(expr, found),
+        }
+    }
+
+    fn expect_number(&mut self, expr: &Expression, found: &Type){
+        match self.shallow(found){
+            Type::Var(_) | Type::Basic{..} => {},
+            other => {
+                let label = format!("expected a number, found {}", self.resolve(&other));
+                self.error(expr, format!("'{}' expects a number", expr), label);
+            },
+        }
+    }
+
+    /// Settles the types of the literals once the code around them has been
+    /// checked. An integer literal nothing is known about becomes an `i32`, a
+    /// float literal an `f64`.
+    fn type_literals(&mut self){
+        use source_ast::ExpressionKind::{Number, Float};
+        let literals = ::std::mem::replace(&mut self.literals, Vec::new());
+        for (expr, found) in literals{
+            match expr.kind{
+                Number{value, ref typ, ..} => {
+                    if let Type::Var(_) = self.shallow(&found){
+                        self.unify(&Type::Basic{kind: BasicKind::Signed, width: 32}, &found);
+                    }
+                    match self.shallow(&found){
+                        Type::Basic{kind, width} if kind != BasicKind::Float => {
+                            if !value.map_or(false, |value| fits(value, kind, width)){
+                                let label = format!("does not fit into {}{}", kind, width);
+                                self.error(expr, "literal out of range", label);
+                            }
+                            typ.set(Some((kind, width)));
+                        },
+                        other => {
+                            let label = format!("expected {}, found an integer literal", self.resolve(&other));
+                            self.error(expr, "mismatched types", label);
+                        },
+                    }
+                },
+                Float{ref width, ..} => {
+                    if let Type::Var(_) = self.shallow(&found){
+                        self.unify(&Type::Basic{kind: BasicKind::Float, width: 64}, &found);
+                    }
+                    match self.shallow(&found){
+                        Type::Basic{kind: BasicKind::Float, width: found} => width.set(Some(found)),
+                        other => {
+                            let label = format!("expected {}, found a float literal", self.resolve(&other));
+                            self.error(expr, "mismatched types", label);
+                        },
+                    }
+                },
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    /// Numbers can be converted into each other, pointers into pointers and
+    /// integers and integers into pointers.
+    fn check_casts(&mut self){
+        use source_ast::ExpressionKind::Cast;
+        let casts = ::std::mem::replace(&mut self.casts, Vec::new());
+        for (expr, found) in casts{
+            let target = match expr.kind{
+                Cast(ref target) => Type::from(target),
+                _ => unreachable!(),
+            };
+            let found = self.resolve(&found);
+            let possible = match (&found, &target){
+                (&Type::Basic{..}, &Type::Basic{..}) => true,
+                (&Type::Pointer(_), &Type::Pointer(_)) => true,
+                (&Type::Pointer(_), &Type::Basic{kind, ..}) | (&Type::Basic{kind, ..}, &Type::Pointer(_)) => kind != BasicKind::Float,
+                _ => false,
+            };
+            if !possible{
+                let label = format!("cannot convert {} to {}", found, target);
+                self.error(expr, "invalid cast", label);
+            }
+        }
+    }
+
+    fn error<M: Into<String>, L: Into<String>>(&mut self, expr: &Expression, message: M, label: L){
+        let mut error = Diagnostic::error(message).with_primary(expr.span, label);
+        if let Some(declaration) = self.declaration{
+            let declared = format!("'{}' is declared here", self.function);
+            error = error.with_secondary(declaration, declared);
+        }
+        self.errors.push(error);
+    }
+}
+
+/// Whether an integer literal can be represented in the given type. `i1`
+/// takes `-1` as well as `1` for true.
+fn fits(value: i128, kind: BasicKind, width: u32) -> bool{
+    match kind{
+        BasicKind::Signed if width == 1 => -1 <= value && value <= 1,
+        BasicKind::Signed if width < 128 => {
+            let half = 1i128 << (width - 1);
+            -half <= value && value < half
+        },
+        BasicKind::Unsigned if width < 127 => 0 <= value && value < 1i128 << width,
+        BasicKind::Unsigned => 0 <= value,
+        _ => true,
+    }
+}
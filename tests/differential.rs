@@ -0,0 +1,259 @@
+//! Runs Sima programs both compiled through LLVM and in the interpreter, and
+//! fails if their output or exit code differ. The corpus is every `.sm` file
+//! under `examples/` and `tests/programs/`; a `<name>.stdin` file next to a
+//! program is fed to it on standard input. On top of that, randomly
+//! generated programs are checked. `SIMA_SEED` picks the first seed and
+//! `SIMA_RANDOM_PROGRAMS` how many programs are generated.
+
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, Stdio};
+
+const SIMA: &str = env!("CARGO_BIN_EXE_sima");
+
+#[derive(Debug, PartialEq)]
+struct Outcome {
+    stdout: Vec<u8>,
+    /// `None` if the program was killed by a signal.
+    code: Option<i32>,
+}
+
+fn execute(command: &mut Command, stdin: &[u8]) -> Outcome {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .expect("could not start the program");
+    child.stdin.take().unwrap().write_all(stdin).expect("could not write the program's input");
+    let output = child.wait_with_output().expect("could not wait for the program");
+    Outcome { stdout: output.stdout, code: output.status.code() }
+}
+
+/// A directory for the executables of one test, which the test removes when
+/// it passes.
+fn scratch_dir(test: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("sima-differential-{}-{}", test, process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn compiled(program: &Path, stdin: &[u8], dir: &Path) -> Result<Outcome, String> {
+    let exe = dir.join(program.file_stem().unwrap());
+    let build = Command::new(SIMA)
+        .arg("--emit=exe")
+        .arg("-o")
+        .arg(&exe)
+        .arg(program)
+        .output()
+        .unwrap();
+    if !build.status.success() {
+        return Err(String::from_utf8_lossy(&build.stderr).into_owned());
+    }
+    Ok(execute(&mut Command::new(&exe), stdin))
+}
+
+fn interpreted(program: &Path, stdin: &[u8]) -> Outcome {
+    execute(Command::new(SIMA).arg("interp").arg(program), stdin)
+}
+
+/// Runs a program both ways, returning a description of how they differ.
+fn compare(program: &Path, dir: &Path) -> Result<(), String> {
+    let stdin = fs::read(program.with_extension("stdin")).unwrap_or_default();
+    let compiled = compiled(program, &stdin, dir)
+        .map_err(|stderr| format!("{} does not compile:\n{}", program.display(), stderr))?;
+    let interpreted = interpreted(program, &stdin);
+    if compiled == interpreted {
+        return Ok(());
+    }
+    Err(format!(
+        "{} behaves differently\ncompiled:    exit code {:?}, stdout {:?}\ninterpreted: exit code {:?}, stdout {:?}",
+        program.display(),
+        compiled.code,
+        String::from_utf8_lossy(&compiled.stdout),
+        interpreted.code,
+        String::from_utf8_lossy(&interpreted.stdout),
+    ))
+}
+
+fn programs(dir: &Path, found: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            programs(&path, found);
+        } else if path.extension().map_or(false, |e| e == "sm") {
+            found.push(path);
+        }
+    }
+}
+
+fn corpus() -> Vec<PathBuf> {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let mut found = Vec::new();
+    programs(&root.join("examples"), &mut found);
+    programs(&root.join("tests/programs"), &mut found);
+    found.sort();
+    found
+}
+
+#[test]
+fn corpus_agrees() {
+    let dir = scratch_dir("corpus");
+    let failures: Vec<String> = corpus().iter()
+        .filter_map(|program| compare(program, &dir).err())
+        .collect();
+    fs::remove_dir_all(&dir).unwrap();
+    assert!(failures.is_empty(), "\n{}", failures.join("\n\n"));
+}
+
+#[test]
+fn random_programs_agree() {
+    let first: u64 = env::var("SIMA_SEED").ok().and_then(|s| s.parse().ok()).unwrap_or(1);
+    let count: u64 = env::var("SIMA_RANDOM_PROGRAMS").ok().and_then(|s| s.parse().ok()).unwrap_or(50);
+    let dir = scratch_dir("random");
+    for seed in first..first + count {
+        let program = dir.join(format!("random{}.sm", seed));
+        fs::write(&program, Generator::new(seed).program()).unwrap();
+        if let Err(message) = compare(&program, &dir) {
+            panic!("seed {}: {}\n\n{}", seed, message, fs::read_to_string(&program).unwrap());
+        }
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Generates well-typed programs on `i32`s. The generator keeps track of the
+/// stack effect of everything it writes, so that it can nest code under `,`,
+/// `if`, `times` and `while`, which is where the depth handling of the
+/// backends can go wrong. It avoids division, so no program has undefined
+/// behaviour.
+struct Generator {
+    state: u64,
+}
+
+/// A piece of code with the number of values it takes and leaves.
+struct Code {
+    text: String,
+    takes: usize,
+    leaves: usize,
+}
+
+/// Bodies for `times` and `while`, which have to leave as many values as they
+/// take.
+const LOOP_BODIES: &[(&str, usize)] = &[
+    ("1 +", 1),
+    ("dup *", 1),
+    ("3 -", 1),
+    ("neg", 1),
+    ("swap", 2),
+    ("swap 2 *", 2),
+    ("(1 +), (2 *)", 2),
+];
+
+impl Generator {
+    fn new(seed: u64) -> Generator {
+        Generator { state: seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407) }
+    }
+
+    /// A number below `n`, from a linear congruential generator.
+    fn below(&mut self, n: u64) -> u64 {
+        self.state = self.state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (self.state >> 33) % n
+    }
+
+    fn program(&mut self) -> String {
+        let mut body = self.code(0, 4);
+        // main returns a single value
+        if body.leaves == 0 {
+            body.text += " 0";
+            body.leaves = 1;
+        }
+        for _ in 1..body.leaves {
+            body.text += " +";
+        }
+        format!("module random;\n\nputchar :: i32 -> i32;\n\nmain :: -> i32;\nmain = {};\n", body.text)
+    }
+
+    /// Code that runs on a stack of `available` values.
+    fn code(&mut self, available: usize, fuel: u32) -> Code {
+        let mut words: Vec<String> = Vec::new();
+        let mut size = available;
+        let mut lowest = available;
+        let steps = 1 + self.below(6);
+        for _ in 0..steps {
+            let word = self.word(size, fuel);
+            size -= word.takes;
+            lowest = lowest.min(size);
+            size += word.leaves;
+            words.push(word.text);
+        }
+        Code {
+            text: words.join(" "),
+            takes: available - lowest,
+            leaves: size - lowest,
+        }
+    }
+
+    /// A single word, or a nested construct if there is fuel left.
+    fn word(&mut self, size: usize, fuel: u32) -> Code {
+        let code = |text: String, takes, leaves| Code { text, takes, leaves };
+        loop {
+            match self.below(if fuel == 0 { 8 } else { 12 }) {
+                0 | 1 => return code(self.below(100).to_string(), 0, 1),
+                2 if size >= 2 => {
+                    let operator = ["+", "-", "*"][self.below(3) as usize];
+                    return code(operator.to_string(), 2, 1);
+                },
+                3 if size >= 1 => return code("dup".to_string(), 1, 2),
+                4 if size >= 2 => return code("swap".to_string(), 2, 2),
+                5 if size >= 2 => return code("drop".to_string(), 1, 0),
+                6 if size >= 1 => return code("neg".to_string(), 1, 1),
+                7 if size >= 1 => return code("dup putchar drop".to_string(), 1, 1),
+                8 => {
+                    // the right side takes its inputs from the top, the left
+                    // side runs on what is below them
+                    let right = self.code(size.min(2), fuel - 1);
+                    let left = self.code(size - right.takes, fuel - 1);
+                    let text = format!("({}), ({})", left.text, right.text);
+                    return code(text, left.takes + right.takes, left.leaves + right.leaves);
+                },
+                9 if size >= 1 => {
+                    // both branches need the same stack effect
+                    let then = self.code(size - 1, fuel - 1);
+                    if then.leaves == 0 {
+                        continue;
+                    }
+                    let text = format!("[{}] [{} 1 +] if", then.text, then.text);
+                    return code(text, then.takes + 1, then.leaves);
+                },
+                10 if size >= 1 => {
+                    let (body, carried) = LOOP_BODIES[self.below(LOOP_BODIES.len() as u64) as usize];
+                    if size < carried {
+                        continue;
+                    }
+                    // the count goes below the carried values, which the
+                    // right side of `,` takes without changing them
+                    let keep = if carried == 1 { "dup drop" } else { "swap swap" };
+                    let text = format!("({}), ({}) [{}] times", self.below(4), keep, body);
+                    return code(text, carried, carried);
+                },
+                11 if size >= 1 => {
+                    let (body, carried) = LOOP_BODIES[self.below(LOOP_BODIES.len() as u64) as usize];
+                    if size < carried {
+                        continue;
+                    }
+                    // a counter on top of the carried values ends the loop;
+                    // the body runs below it
+                    let text = format!("{} [dup 0 >] [({}), (1 -)] while drop", self.below(4), body);
+                    return code(text, carried, carried);
+                },
+                _ => {},
+            }
+        }
+    }
+}
@@ -0,0 +1,138 @@
+//! Compiles every `.sm` file under `examples/` and `tests/programs/`, runs
+//! the programs that compile and compares the results with the files next
+//! to them:
+//!
+//! * `<name>.stdout` holds what the program prints,
+//! * `<name>.stderr` holds the compiler's diagnostics, or, if the program
+//!   compiles, what it writes to stderr followed by its exit status if that
+//!   is not 0,
+//! * `<name>.ll` holds the LLVM IR and is only compared if it exists. The
+//!   IR is emitted twice and has to come out the same both times.
+//!
+//! A missing `.stdout` or `.stderr` file means the output is expected to be
+//! empty. `<name>.stdin` is fed to the program on standard input. Run with
+//! `SIMA_BLESS=1` to write the current results as the expected ones.
+
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, Output, Stdio};
+
+const SIMA: &str = env!("CARGO_BIN_EXE_sima");
+
+fn root() -> &'static Path {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+}
+
+fn programs(dir: &Path, found: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            programs(&path, found);
+        } else if path.extension().map_or(false, |e| e == "sm") {
+            found.push(path);
+        }
+    }
+}
+
+/// Runs the compiler from the crate root on a path relative to it, so that
+/// diagnostics name the file the same way on every machine.
+fn sima(args: &[&str], program: &Path) -> Output {
+    Command::new(SIMA)
+        .current_dir(root())
+        .args(args)
+        .arg(program)
+        .output()
+        .unwrap()
+}
+
+fn run(exe: &Path, stdin: &[u8]) -> Output {
+    let mut child = Command::new(exe)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("could not start the program");
+    child.stdin.take().unwrap().write_all(stdin).expect("could not write the program's input");
+    child.wait_with_output().expect("could not wait for the program")
+}
+
+/// The actual results for a program, by the extension of the file that
+/// holds the expected ones.
+fn results(program: &Path, scratch: &Path) -> Vec<(&'static str, Vec<u8>)> {
+    let stem = program.file_stem().unwrap().to_str().unwrap();
+    let exe = scratch.join(stem);
+    let build = sima(&["--emit=exe", "-o", exe.to_str().unwrap()], program);
+    if !build.status.success() {
+        return vec![("stdout", Vec::new()), ("stderr", build.stderr)];
+    }
+    let stdin = fs::read(root().join(program).with_extension("stdin")).unwrap_or_default();
+    let output = run(&exe, &stdin);
+    let mut stderr = output.stderr;
+    match output.status.code() {
+        Some(0) => {},
+        Some(code) => stderr.extend(format!("exit status: {}\n", code).bytes()),
+        None => stderr.extend(b"killed by a signal\n".iter()),
+    }
+    let mut results = vec![("stdout", output.stdout), ("stderr", stderr)];
+
+    let expected_ir = root().join(program).with_extension("ll");
+    if expected_ir.exists() {
+        let first = emit_ir(program, &scratch.join(format!("{}.ll", stem)));
+        let second = emit_ir(program, &scratch.join(format!("{}.again.ll", stem)));
+        assert!(first == second, "{} does not compile to the same IR twice", program.display());
+        results.push(("ll", first));
+    }
+    results
+}
+
+fn emit_ir(program: &Path, ir: &Path) -> Vec<u8> {
+    let emit = sima(&["--emit=llvm-ir", "-o", ir.to_str().unwrap()], program);
+    assert!(emit.status.success(), "{}", String::from_utf8_lossy(&emit.stderr));
+    fs::read(ir).unwrap()
+}
+
+#[test]
+fn golden() {
+    let bless = env::var("SIMA_BLESS").map(|v| v == "1").unwrap_or(false);
+    let scratch = env::temp_dir().join(format!("sima-golden-{}", process::id()));
+    fs::create_dir_all(&scratch).unwrap();
+
+    let mut all = Vec::new();
+    programs(&root().join("examples"), &mut all);
+    programs(&root().join("tests/programs"), &mut all);
+    all.sort();
+
+    let mut failures = Vec::new();
+    for path in all {
+        let program = path.strip_prefix(root()).unwrap();
+        for (extension, actual) in results(program, &scratch) {
+            let expected_file = path.with_extension(extension);
+            let expected = fs::read(&expected_file).unwrap_or_default();
+            if actual == expected {
+                continue;
+            }
+            if bless {
+                if actual.is_empty() {
+                    fs::remove_file(&expected_file).unwrap();
+                } else {
+                    fs::write(&expected_file, &actual).unwrap();
+                }
+            } else {
+                failures.push(format!(
+                    "{} differs\n--- expected\n{}\n--- actual\n{}",
+                    expected_file.strip_prefix(root()).unwrap().display(),
+                    String::from_utf8_lossy(&expected),
+                    String::from_utf8_lossy(&actual),
+                ));
+            }
+        }
+    }
+    fs::remove_dir_all(&scratch).unwrap();
+    assert!(failures.is_empty(), "run with SIMA_BLESS=1 to accept the changes\n\n{}", failures.join("\n\n"));
+}